@@ -1,5 +1,9 @@
 use std::{
-    collections::HashMap, env, ops::Deref, path::{Path, PathBuf}, str::FromStr
+    collections::HashMap,
+    env,
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use chin_tools::{aanyhow, AResult};
@@ -14,6 +18,7 @@ pub struct CommonConfig {
 pub struct Config {
     pub db: DatabaseConfig,
     pub dict: Option<DictConfig>,
+    pub calc: Option<CalcConfig>,
     pub ui: Option<UI>,
     pub common: CommonConfig,
 }
@@ -26,6 +31,53 @@ pub struct DatabaseConfig {
 #[derive(Debug, Clone, Deserialize)]
 pub struct DictConfig {
     pub dir_path: String,
+    /// Settings of single dictionaries, the others are enabled with no priority.
+    pub dictionaries: Option<Vec<DictionaryConfig>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DictionaryConfig {
+    /// File name of the dictionary, or its path relative to `dir_path`.
+    pub file: String,
+    pub enabled: Option<bool>,
+    /// Higher ones are shown first.
+    pub priority: Option<i32>,
+    /// Shown instead of the name the dictionary gives itself.
+    pub name: Option<String>,
+    /// Like `en-zh`, shown next to the name.
+    pub languages: Option<String>,
+    /// Stylesheet applied to its entries, relative to `dir_path`.
+    pub css: Option<String>,
+    /// Registration code of an encrypted MDict, in hex.
+    pub regcode: Option<String>,
+    /// Email or device id the registration code was issued for.
+    pub email: Option<String>,
+}
+
+impl DictConfig {
+    /// Settings of the dictionary at `path`, if there are any.
+    pub fn dictionary(&self, path: &Path) -> Option<&DictionaryConfig> {
+        let relative = path.strip_prefix(&self.dir_path).ok();
+        self.dictionaries.as_ref()?.iter().find(|d| {
+            let file = Path::new(&d.file);
+            Some(file) == relative
+                || file == path
+                || (file.components().count() == 1 && path.file_name() == Some(file.as_os_str()))
+        })
+    }
+
+    /// Dictionaries are enabled unless their settings say otherwise.
+    pub fn enabled(&self, path: &Path) -> bool {
+        self.dictionary(path)
+            .and_then(|d| d.enabled)
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CalcConfig {
+    /// Significant digits of results which aren't whole numbers.
+    pub precision: Option<usize>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
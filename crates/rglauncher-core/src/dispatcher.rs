@@ -133,7 +133,7 @@ impl PluginDispatcher {
         let clip = ClipPlugin::new()?.into();
         #[cfg(feature = "fmdict")]
         let dict = DictPlugin::new(config.dict.as_ref())?.into();
-        let calc = CalcPlugin::new()?.into();
+        let calc = CalcPlugin::new(config.calc.as_ref())?.into();
 
         Ok(PluginDispatcher {
             app,
@@ -176,12 +176,12 @@ impl PluginDispatcher {
 
                     #[cfg(feature = "clip")]
                     {
-                        handle_refresh!(self.clip);
+                        handle_refresh!(executor, self.clip);
                     }
 
                     #[cfg(feature = "fmdict")]
                     {
-                        handle_refresh!(self.dict);
+                        handle_refresh!(executor, self.dict);
                     }
                 }
                 DispatchMsg::SetHistory(prwrapper) => {
@@ -215,6 +215,8 @@ impl PluginDispatcher {
                                 update_time: Utc::now().naive_utc(),
                             });
                         }
+                        #[cfg(feature = "fmdict")]
+                        crate::plugins::PluginResultEnum::MDict(_) => {}
                     }
                 }
                 DispatchMsg::PluginMsg => {}
@@ -12,4 +12,7 @@ pub enum ResultMsg {
     UserInput(UserInput),
     ChangeSelect(u32),
     SelectSomething,
+    SelectAlternative,
+    /// Run one of the selected result's `actions`.
+    SelectAction(usize),
 }
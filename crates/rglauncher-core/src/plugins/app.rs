@@ -34,10 +34,50 @@ pub struct AppResult {
     pub id: SharedStr,
     pub desktop_path: SharedStr,
     pub terminal: bool,
+    /// The window class or app id its windows are expected to have.
+    #[serde(default)]
+    pub startup_wm_class: Option<SharedStr>,
 }
 
 lazy_static! {
     static ref PLACE_HOLDER_REPLACER: Regex = Regex::new(r"%\w").unwrap();
+    /// Shared with the window switcher to name and iconify windows.
+    static ref APPLICATIONS: ArcSwap<Vec<AppResult>> =
+        ArcSwap::new(AppPlugin::read_applications().into());
+}
+
+/// The desktop entry a window with the class or app id `app_id` belongs to.
+pub fn find_by_app_id(app_id: &str) -> Option<AppResult> {
+    match_app_id(&APPLICATIONS.load(), app_id).cloned()
+}
+
+/// Tried in order: `StartupWMClass`, the desktop id, the last part of a reverse DNS
+/// desktop id and the executable name, all ignoring case.
+fn match_app_id<'a>(apps: &'a [AppResult], app_id: &str) -> Option<&'a AppResult> {
+    if app_id.is_empty() {
+        return None;
+    }
+    let is = |s: &str| s.eq_ignore_ascii_case(app_id);
+
+    apps.iter()
+        .find(|app| app.startup_wm_class.as_ref().is_some_and(|c| is(c)))
+        .or_else(|| apps.iter().find(|app| is(&app.id)))
+        .or_else(|| {
+            apps.iter()
+                .find(|app| app.id.rsplit('.').next().is_some_and(is))
+        })
+        .or_else(|| {
+            apps.iter()
+                .find(|app| executable(&app.exec).is_some_and(|e| is(&e)))
+        })
+}
+
+/// `env FOO=1 /usr/bin/code --flag %F` is `code`.
+fn executable(exec: &str) -> Option<String> {
+    let program = parse_cmd_string(exec)
+        .into_iter()
+        .find(|arg| arg != "env" && !arg.contains('='))?;
+    Some(program.rsplit('/').next()?.to_string())
 }
 
 pub const TYPE_ID: &str = "app_result";
@@ -97,7 +137,6 @@ impl PluginResult for AppResult {
 }
 
 pub struct AppPlugin {
-    applications: ArcSwap<Vec<AppResult>>,
     history: HistoryCache<AppResult>,
     matcher: SkimMatcherV2,
 }
@@ -107,13 +146,10 @@ impl AppPlugin {
         info!("Creating App Plugin");
         let matcher = SkimMatcherV2::default();
 
-        let applications = ArcSwap::new(Self::read_applications().into());
-
         let histories: Vec<HistoryItem<AppResult>> =
             CONNECTION.with_borrow(|e| HistoryDb::new(e.as_ref()).fetch_histories(TYPE_ID))?;
 
         Ok(AppPlugin {
-            applications,
             matcher,
             history: HistoryCache::new(histories),
         })
@@ -140,6 +176,7 @@ impl AppPlugin {
                         exec: entry.exec()?.into(),
                         desktop_path: path.to_str()?.to_owned().into(),
                         terminal: entry.terminal(),
+                        startup_wm_class: entry.startup_wm_class().map(Into::into),
                     });
                 }
 
@@ -163,12 +200,11 @@ impl Plugin for AppPlugin {
                 ho,
             )
         });
-        self.applications.store(applications);
+        APPLICATIONS.store(applications);
     }
 
     fn handle_input(&self, user_input: &UserInput) -> AResult<Vec<(AppResult, i32)>> {
-        let result = self
-            .applications
+        let result = APPLICATIONS
             .load()
             .iter()
             .filter_map(|app| {
@@ -225,3 +261,53 @@ fn run_command(command_and_args: Vec<&str>) -> io::Result<()> {
             .map(|_| ())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{executable, match_app_id, AppResult};
+
+    fn app(id: &str, exec: &str, startup_wm_class: Option<&str>) -> AppResult {
+        AppResult {
+            icon_name: id.into(),
+            app_name: id.into(),
+            app_desc: "".into(),
+            exec: exec.into(),
+            id: id.into(),
+            desktop_path: format!("/usr/share/applications/{}.desktop", id).into(),
+            terminal: false,
+            startup_wm_class: startup_wm_class.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn test_match_app_id() {
+        let apps = vec![
+            app("org.mozilla.firefox", "firefox %u", None),
+            app(
+                "code",
+                "env GDK_BACKEND=x11 /usr/share/code/code %F",
+                Some("Code"),
+            ),
+            app("org.gnome.Nautilus", "nautilus --new-window %U", None),
+            app("org.wezfurlong.wezterm", "wezterm start --cwd .", None),
+        ];
+        let found = |app_id| match_app_id(&apps, app_id).map(|app| app.id.as_str());
+
+        assert_eq!(found("code"), Some("code"));
+        assert_eq!(found("Code"), Some("code"));
+        assert_eq!(found("org.gnome.Nautilus"), Some("org.gnome.Nautilus"));
+        assert_eq!(found("firefox"), Some("org.mozilla.firefox"));
+        assert_eq!(found("wezterm"), Some("org.wezfurlong.wezterm"));
+        assert_eq!(found("foot"), None);
+        assert_eq!(found(""), None);
+    }
+
+    #[test]
+    fn test_executable() {
+        assert_eq!(
+            executable("env FOO=1 /usr/bin/code --flag %F").as_deref(),
+            Some("code")
+        );
+        assert_eq!(executable("foot").as_deref(), Some("foot"));
+    }
+}
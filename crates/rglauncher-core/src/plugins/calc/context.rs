@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use chin_tools::{aanyhow, AResult, AnyhowContext};
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use tracing::warn;
+
+use super::eval::{self, Quantity};
+use super::parser::{self, Expr, Statement};
+
+pub const ANS: &str = "ans";
+
+#[derive(Clone, Debug)]
+pub struct UserFn {
+    pub params: Vec<String>,
+    pub body: Expr,
+    /// The definition as typed, kept to show and store it.
+    pub source: String,
+}
+
+/// Variables and functions the user has defined, `ans` included.
+#[derive(Clone, Debug, Default)]
+pub struct CalcContext {
+    pub vars: BTreeMap<String, Quantity>,
+    pub funcs: BTreeMap<String, UserFn>,
+}
+
+/// What selecting a calculator result stores into the context.
+#[derive(Clone, Debug)]
+pub enum Binding {
+    Var(String, Quantity),
+    Func(String, UserFn),
+}
+
+impl CalcContext {
+    pub fn is_bound(&self, name: &str) -> bool {
+        self.vars.contains_key(name) || self.funcs.contains_key(name)
+    }
+
+    pub fn bind(&mut self, binding: Binding) {
+        match binding {
+            Binding::Var(name, value) => {
+                self.funcs.remove(&name);
+                self.vars.insert(name, value);
+            }
+            Binding::Func(name, func) => {
+                self.vars.remove(&name);
+                self.funcs.insert(name, func);
+            }
+        }
+    }
+
+    /// `(name, value)` rows for the preview, `ans` first.
+    pub fn table(&self, precision: usize) -> Vec<(String, String)> {
+        let vars = self.vars.iter().map(|(name, value)| {
+            let value = value
+                .format(precision)
+                .unwrap_or_else(|err| err.to_string());
+            (name.clone(), value)
+        });
+        let funcs = self
+            .funcs
+            .values()
+            .map(|func| (func.signature(), func.definition()));
+
+        let mut table: Vec<_> = vars.chain(funcs).collect();
+        table.sort_by_key(|(name, _)| name != ANS);
+        table
+    }
+}
+
+impl UserFn {
+    /// `f(x)` of `f(x) = x^2 + 1`
+    pub fn signature(&self) -> String {
+        match self.source.split_once('=') {
+            Some((signature, _)) => signature.trim().to_string(),
+            None => self.source.clone(),
+        }
+    }
+
+    /// `x^2 + 1` of `f(x) = x^2 + 1`
+    pub fn definition(&self) -> String {
+        match self.source.split_once('=') {
+            Some((_, body)) => body.trim().to_string(),
+            None => self.source.clone(),
+        }
+    }
+}
+
+const KIND_VAR: &str = "var";
+const KIND_FN: &str = "fn";
+
+pub struct ContextDb<'a> {
+    conn: Option<&'a Connection>,
+}
+
+impl<'a> ContextDb<'a> {
+    pub fn new(conn: Option<&'a Connection>) -> Self {
+        ContextDb { conn }
+    }
+
+    pub fn try_create_table(&self) -> AResult<()> {
+        self.conn
+            .context("conn is none")?
+            .prepare(
+                "CREATE TABLE IF NOT EXISTS calc_context (
+name TEXT,
+kind TEXT,
+body_json TEXT,
+update_time TIMESTAMP,
+PRIMARY KEY (name)
+)",
+            )?
+            .execute([])?;
+
+        Ok(())
+    }
+
+    pub fn load(&self) -> AResult<CalcContext> {
+        let mut stmt = self
+            .conn
+            .context("conn is none")?
+            .prepare("select name, kind, body_json from calc_context order by update_time")?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>("name")?,
+                    row.get::<_, String>("kind")?,
+                    row.get::<_, String>("body_json")?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut context = CalcContext::default();
+        for (name, kind, body_json) in rows {
+            match restore(&context, &name, &kind, &body_json) {
+                Ok(binding) => context.bind(binding),
+                Err(err) => warn!("unable to restore calc {} {}: {}", kind, name, err),
+            }
+        }
+
+        Ok(context)
+    }
+
+    pub fn store(&self, binding: &Binding) -> AResult<()> {
+        if let Some(conn) = self.conn {
+            let (name, kind, body_json) = match binding {
+                Binding::Var(name, value) => (name, KIND_VAR, serde_json::to_string(value)?),
+                Binding::Func(name, func) => (name, KIND_FN, serde_json::to_string(&func.source)?),
+            };
+
+            conn.prepare(
+                "insert or replace into calc_context \
+            (name, kind, body_json, update_time) values (?, ?, ?, ?)",
+            )?
+            .insert(params![name, kind, body_json, Utc::now().naive_utc()])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Functions are stored as source, so they are parsed against the rows restored before them.
+fn restore(context: &CalcContext, name: &str, kind: &str, body_json: &str) -> AResult<Binding> {
+    match kind {
+        KIND_VAR => Ok(Binding::Var(
+            name.to_string(),
+            serde_json::from_str(body_json)?,
+        )),
+        KIND_FN => {
+            let source: String = serde_json::from_str(body_json)?;
+            match parser::parse(&source, &|n| context.is_bound(n))? {
+                Statement::Define { params, body, .. } => Ok(Binding::Func(
+                    name.to_string(),
+                    UserFn {
+                        params,
+                        body,
+                        source,
+                    },
+                )),
+                _ => Err(aanyhow!("not a function: {}", source)),
+            }
+        }
+        _ => Err(aanyhow!("unknown kind: {}", kind)),
+    }
+}
+
+pub(super) fn check_name(name: &str) -> AResult<()> {
+    if eval::is_constant(name)
+        || eval::is_builtin(name)
+        || parser::CONVERSION_KEYWORDS.contains(&name)
+        || name == parser::XOR_KEYWORD
+    {
+        Err(aanyhow!("{} is reserved", name))
+    } else {
+        Ok(())
+    }
+}
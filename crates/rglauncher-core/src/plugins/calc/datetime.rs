@@ -0,0 +1,486 @@
+use chin_tools::{aanyhow, AResult};
+use chrono::{
+    DateTime, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::context::{Binding, CalcContext, ANS};
+use super::eval::{self, Evaluation, Quantity};
+use super::num::Number;
+use super::units::{self, Dim};
+
+lazy_static! {
+    static ref MOMENT: Regex = Regex::new(
+        r"(?ix)^(
+            now | today | tomorrow | yesterday
+            | unix \s+ -?\d+
+            | \d{4}-\d{2}-\d{2} (?: [T\s] \d{1,2}:\d{2} (?: :\d{2} )? )?
+            | \d{1,2}:\d{2} (?: :\d{2} )?
+        )\b"
+    )
+    .unwrap();
+    /// Months and years move the calendar, `jan 31 + 1 month` is the end of february.
+    static ref CALENDAR: Regex =
+        Regex::new(r"(?i)^(\d+)\s*(months?|mo|years?|yr)$").unwrap();
+}
+
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Zone {
+    Local,
+    Named(Tz),
+}
+
+impl Zone {
+    /// `UTC`, `Asia/Tokyo` or just the city, `tokyo`, `new york`.
+    fn lookup(name: &str) -> Option<Zone> {
+        let name = name.trim().replace(' ', "_").to_lowercase();
+        if name.is_empty() {
+            return None;
+        }
+        match name.as_str() {
+            "local" => return Some(Zone::Local),
+            "utc" | "gmt" | "z" => return Some(Zone::Named(Tz::UTC)),
+            _ => {}
+        }
+        TZ_VARIANTS
+            .iter()
+            .find(|tz| {
+                let full = tz.name().to_lowercase();
+                full == name || full.rsplit('/').next() == Some(name.as_str())
+            })
+            .map(|tz| Zone::Named(*tz))
+    }
+
+    fn resolve(&self, naive: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Local => Local
+                .from_local_datetime(naive)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+            Zone::Named(tz) => tz
+                .from_local_datetime(naive)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+        }
+    }
+
+    fn wall_clock(&self, moment: &DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Local => moment.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => moment.with_timezone(tz).naive_local(),
+        }
+    }
+
+    fn format(&self, moment: &DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            Zone::Local => moment.with_timezone(&Local).format(fmt).to_string(),
+            Zone::Named(tz) => moment.with_timezone(tz).format(fmt).to_string(),
+        }
+    }
+
+    fn display(&self, moment: &DateTime<Utc>) -> String {
+        match self {
+            Zone::Local => self.format(moment, DATETIME_FORMAT),
+            Zone::Named(_) => self.format(moment, &format!("{} %Z", DATETIME_FORMAT)),
+        }
+    }
+
+    fn rfc3339(&self, moment: &DateTime<Utc>) -> String {
+        match self {
+            Zone::Local => moment.with_timezone(&Local).to_rfc3339(),
+            Zone::Named(tz) => moment.with_timezone(tz).to_rfc3339(),
+        }
+    }
+}
+
+enum Value {
+    Moment(DateTime<Utc>, Zone),
+    Span(Duration),
+}
+
+enum Target {
+    Zone(Zone),
+    Week,
+}
+
+/// Dates, times and their differences, `None` when the input doesn't start with one.
+pub fn evaluate(
+    input: &str,
+    ctx: &CalcContext,
+    precision: usize,
+    now: DateTime<Utc>,
+) -> AResult<Option<Evaluation>> {
+    let mut input = input.trim();
+    let mut target = None;
+
+    if let Some(rest) = strip_word(input, "week") {
+        input = rest;
+        target = Some(Target::Week);
+    }
+    if !MOMENT.is_match(input) {
+        return Ok(None);
+    }
+    if let Some((rest, zone)) = split_target_zone(input) {
+        input = rest;
+        target = target.or(Some(Target::Zone(zone)));
+    }
+
+    let value = expression(input, ctx, now)?;
+    let evaluation = match (value, target) {
+        (Value::Moment(moment, _), Some(Target::Week)) => Evaluation {
+            display: Zone::Local.format(&moment, "%G-W%V"),
+            details: moment_details(&moment, Zone::Local, now),
+            binding: None,
+        },
+        (Value::Moment(moment, zone), target) => {
+            let zone = match target {
+                Some(Target::Zone(zone)) => zone,
+                _ => zone,
+            };
+            Evaluation {
+                display: zone.display(&moment),
+                details: moment_details(&moment, zone, now),
+                binding: None,
+            }
+        }
+        (Value::Span(span), None) => span_evaluation(span, precision)?,
+        (Value::Span(_), Some(_)) => return Err(aanyhow!("a duration has no time zone")),
+    };
+
+    Ok(Some(evaluation))
+}
+
+/// `moment [zone] [(+ | -) (duration | moment)]`
+fn expression(input: &str, ctx: &CalcContext, now: DateTime<Utc>) -> AResult<Value> {
+    let (moment, zone, rest) = moment(input, now)?;
+    let rest = rest.trim_start();
+
+    let Some(op) = rest.chars().next() else {
+        return Ok(Value::Moment(moment, zone));
+    };
+    let operand = rest[op.len_utf8()..].trim();
+    match op {
+        '-' if MOMENT.is_match(operand) => match moment_exact(operand, now)? {
+            Some(other) => Ok(Value::Span(moment - other)),
+            None => Err(aanyhow!("unable to parse {}", operand)),
+        },
+        '+' | '-' => {
+            let negative = op == '-';
+            Ok(Value::Moment(
+                shift(moment, zone, operand, negative, ctx)?,
+                zone,
+            ))
+        }
+        _ => Err(aanyhow!("unexpected {}", rest)),
+    }
+}
+
+/// A moment followed by nothing but its zone.
+fn moment_exact(input: &str, now: DateTime<Utc>) -> AResult<Option<DateTime<Utc>>> {
+    let (moment, _, rest) = moment(input, now)?;
+    Ok(rest.trim().is_empty().then_some(moment))
+}
+
+/// Parses the leading moment and its optional zone, returning what is left.
+fn moment(input: &str, now: DateTime<Utc>) -> AResult<(DateTime<Utc>, Zone, &str)> {
+    let literal = MOMENT
+        .find(input)
+        .ok_or_else(|| aanyhow!("expected a date or time: {}", input))?;
+    let rest = &input[literal.end()..];
+
+    // the zone runs up to the operator, `14:30 new york + 2h`
+    let zone_end = operator_at(rest).unwrap_or(rest.len());
+    let (zone, rest) = match rest[..zone_end].trim() {
+        "" => (None, rest),
+        name => (
+            Some(Zone::lookup(name).ok_or_else(|| aanyhow!("unknown time zone: {}", name))?),
+            &rest[zone_end..],
+        ),
+    };
+
+    let literal = literal.as_str().to_lowercase();
+    let parse_zone = zone.unwrap_or(Zone::Local);
+    let today = parse_zone.wall_clock(&now).date();
+    let at_midnight = |date: NaiveDate| {
+        parse_zone
+            .resolve(&date.and_time(NaiveTime::MIN))
+            .ok_or_else(|| aanyhow!("{} has no midnight", date))
+    };
+
+    let moment = match literal.as_str() {
+        "now" => now,
+        "today" => at_midnight(today)?,
+        "tomorrow" => at_midnight(today + Duration::days(1))?,
+        "yesterday" => at_midnight(today - Duration::days(1))?,
+        literal => {
+            if let Some(seconds) = literal.strip_prefix("unix") {
+                let seconds: i64 = seconds.trim().parse()?;
+                DateTime::from_timestamp(seconds, 0)
+                    .ok_or_else(|| aanyhow!("timestamp out of range: {}", seconds))?
+            } else {
+                let naive = parse_naive(literal, today)?;
+                parse_zone
+                    .resolve(&naive)
+                    .ok_or_else(|| aanyhow!("{} doesn't exist in that zone", naive))?
+            }
+        }
+    };
+
+    // timestamps are shown locally unless a zone was asked for
+    Ok((moment, zone.unwrap_or(Zone::Local), rest))
+}
+
+fn parse_naive(literal: &str, today: NaiveDate) -> AResult<NaiveDateTime> {
+    let time = |s: &str| {
+        NaiveTime::parse_from_str(s, "%H:%M:%S").or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+    };
+
+    if literal.contains('-') {
+        let (date, clock) = match literal.split_once(['t', ' ']) {
+            Some((date, clock)) => (date, Some(clock.trim())),
+            None => (literal, None),
+        };
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        let clock = match clock {
+            Some(clock) => time(clock)?,
+            None => NaiveTime::MIN,
+        };
+        Ok(date.and_time(clock))
+    } else {
+        Ok(today.and_time(time(literal)?))
+    }
+}
+
+/// Moves a moment by `3 weeks`, `90 min`, `1 month`...
+fn shift(
+    moment: DateTime<Utc>,
+    zone: Zone,
+    operand: &str,
+    negative: bool,
+    ctx: &CalcContext,
+) -> AResult<DateTime<Utc>> {
+    if let Some(caps) = CALENDAR.captures(operand) {
+        let count: u32 = caps[1].parse()?;
+        let months = if caps[2].to_lowercase().starts_with('y') {
+            count.checked_mul(12)
+        } else {
+            Some(count)
+        }
+        .map(Months::new)
+        .ok_or_else(|| aanyhow!("too many months"))?;
+
+        // calendar arithmetic happens on the wall clock of the zone
+        let wall = zone.wall_clock(&moment);
+        let wall = if negative {
+            wall.checked_sub_months(months)
+        } else {
+            wall.checked_add_months(months)
+        }
+        .ok_or_else(|| aanyhow!("date out of range"))?;
+        return zone
+            .resolve(&wall)
+            .ok_or_else(|| aanyhow!("{} doesn't exist in that zone", wall));
+    }
+
+    let span = eval::quantity(operand, ctx)?;
+    if span.dim != Dim::TIME {
+        return Err(aanyhow!("{} is not a duration", operand));
+    }
+    let millis = span.si.mul(&Number::int(1000)).to_f64().round();
+    let span = Some(millis)
+        .filter(|m| m.is_finite())
+        .and_then(|m| Duration::try_milliseconds(m as i64))
+        .ok_or_else(|| aanyhow!("duration out of range"))?;
+    let span = if negative { -span } else { span };
+
+    moment
+        .checked_add_signed(span)
+        .ok_or_else(|| aanyhow!("date out of range"))
+}
+
+fn moment_details(moment: &DateTime<Utc>, zone: Zone, now: DateTime<Utc>) -> Vec<(String, String)> {
+    let mut details = vec![
+        ("Weekday".to_string(), zone.format(moment, "%A")),
+        ("ISO week".to_string(), zone.format(moment, "%G-W%V-%u")),
+        ("Day of year".to_string(), zone.format(moment, "%j")),
+        ("ISO 8601".to_string(), zone.rfc3339(moment)),
+        ("Unix".to_string(), moment.timestamp().to_string()),
+        ("UTC".to_string(), Zone::Named(Tz::UTC).display(moment)),
+    ];
+    if zone != Zone::Local {
+        details.push(("Local".to_string(), Zone::Local.display(moment)));
+    }
+    details.push(("Relative".to_string(), relative(*moment - now)));
+    details
+}
+
+fn span_evaluation(span: Duration, precision: usize) -> AResult<Evaluation> {
+    let seconds = Number::int(span.num_milliseconds()).div(&Number::int(1000))?;
+    let in_unit = |name: &str| -> AResult<String> {
+        let unit = units::lookup(name).ok_or_else(|| aanyhow!("unknown unit: {}", name))?;
+        Ok(format!(
+            "{} {}",
+            unit.of_si(&seconds)?.format(precision),
+            unit.symbol
+        ))
+    };
+
+    let details = vec![
+        ("Days".to_string(), in_unit("d")?),
+        ("Weeks".to_string(), in_unit("wk")?),
+        ("Hours".to_string(), in_unit("h")?),
+        ("Seconds".to_string(), in_unit("s")?),
+    ];
+    let value = Quantity {
+        si: seconds,
+        dim: Dim::TIME,
+        unit: units::lookup("d"),
+    };
+
+    Ok(Evaluation {
+        display: format_span(span),
+        details,
+        binding: Some(Binding::Var(ANS.to_string(), value)),
+    })
+}
+
+/// `24 days`, `1 day 2 h 30 min`, `-45 s`
+fn format_span(span: Duration) -> String {
+    let (sign, span) = if span < Duration::zero() {
+        ("-", -span)
+    } else {
+        ("", span)
+    };
+    let parts = [
+        (
+            span.num_days(),
+            if span.num_days() == 1 { "day" } else { "days" },
+        ),
+        (span.num_hours() % 24, "h"),
+        (span.num_minutes() % 60, "min"),
+        (span.num_seconds() % 60, "s"),
+    ];
+    let text = parts
+        .iter()
+        .filter(|(count, _)| *count != 0)
+        .map(|(count, unit)| format!("{} {}", count, unit))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if text.is_empty() {
+        "0 s".to_string()
+    } else {
+        format!("{}{}", sign, text)
+    }
+}
+
+/// `in 3 days`, `2 h ago`, only the largest part.
+fn relative(span: Duration) -> String {
+    if span.num_seconds().abs() < 60 {
+        return "now".to_string();
+    }
+    let largest = format_span(span)
+        .trim_start_matches('-')
+        .split(' ')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if span < Duration::zero() {
+        format!("{} ago", largest)
+    } else {
+        format!("in {}", largest)
+    }
+}
+
+/// `week <moment>`
+fn strip_word<'a>(input: &'a str, word: &str) -> Option<&'a str> {
+    let (head, rest) = input.split_once(char::is_whitespace)?;
+    head.eq_ignore_ascii_case(word).then(|| rest.trim_start())
+}
+
+/// Splits `... in berlin` or `... to utc`, only when the tail names a zone.
+fn split_target_zone(input: &str) -> Option<(&str, Zone)> {
+    // ASCII lowercasing keeps the byte offsets of `input`
+    let lower = input.to_ascii_lowercase();
+    [" in ", " to "]
+        .iter()
+        .filter_map(|keyword| lower.rfind(keyword).map(|at| (at, keyword.len())))
+        .max()
+        .and_then(|(at, len)| {
+            Zone::lookup(&input[at + len..]).map(|zone| (input[..at].trim_end(), zone))
+        })
+}
+
+/// Index of the first `+` or `-` which starts a token, not the one inside `Port-au-Prince`.
+fn operator_at(input: &str) -> Option<usize> {
+    let mut previous = ' ';
+    for (i, c) in input.char_indices() {
+        if (c == '+' || c == '-') && previous.is_whitespace() {
+            return Some(i);
+        }
+        previous = c;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::super::context::CalcContext;
+    use super::super::eval::DEFAULT_PRECISION;
+    use super::evaluate;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    fn display(input: &str) -> String {
+        evaluate(input, &CalcContext::default(), DEFAULT_PRECISION, now())
+            .unwrap()
+            .unwrap()
+            .display
+    }
+
+    #[test]
+    fn test_datetime() {
+        assert_eq!(display("now + 3 weeks in UTC"), "2026-01-22 00:00:00 UTC");
+        assert_eq!(display("now - 90 min to utc"), "2025-12-31 22:30:00 UTC");
+        assert_eq!(
+            display("2026-01-31 UTC + 1 month"),
+            "2026-02-28 00:00:00 UTC"
+        );
+        assert_eq!(display("2026-12-25 - 2026-12-01"), "24 days");
+        assert_eq!(
+            display("2026-12-25 12:00 - 2026-12-24 09:30"),
+            "1 day 2 h 30 min"
+        );
+        assert_eq!(display("unix 1700000000 in UTC"), "2023-11-14 22:13:20 UTC");
+        assert_eq!(
+            display("2026-01-15 14:30 Tokyo in Berlin"),
+            "2026-01-15 06:30:00 CET"
+        );
+        assert_eq!(
+            display("2026-07-15 09:00 new york in Asia/Kolkata"),
+            "2026-07-15 18:30:00 IST"
+        );
+        assert_eq!(display("week 2026-12-25"), "2026-W52");
+        assert_eq!(display("week 2027-01-01"), "2026-W53");
+    }
+
+    #[test]
+    fn test_not_datetime() {
+        let ctx = CalcContext::default();
+        let eval = |input| evaluate(input, &ctx, DEFAULT_PRECISION, now());
+        assert!(eval("1 + 2").unwrap().is_none());
+        assert!(eval("nowhere").unwrap().is_none());
+        assert!(eval("now + 3 kg").is_err());
+        assert!(eval("now + -9223372036854775.808 s").is_err());
+        assert!(eval("14:30 atlantis").is_err());
+        assert!(eval("now İ in ü").is_err());
+    }
+}
@@ -0,0 +1,556 @@
+use std::cmp::Ordering;
+
+use chin_tools::{aanyhow, AResult};
+use serde::{Deserialize, Serialize};
+
+use super::context::{self, Binding, CalcContext, UserFn};
+use super::num::{Base, Number};
+use super::parser::{self, Expr, Statement, Target, SHL, SHR, XOR};
+use super::units::{self, Dim, Unit};
+
+/// Significant digits shown for results which aren't whole numbers.
+pub const DEFAULT_PRECISION: usize = 12;
+
+const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("π", std::f64::consts::PI),
+    ("e", std::f64::consts::E),
+    ("tau", std::f64::consts::TAU),
+];
+
+pub fn is_constant(name: &str) -> bool {
+    CONSTANTS.iter().any(|(n, _)| *n == name)
+}
+
+const BUILTINS: &[&str] = &[
+    "exp", "ln", "log", "log10", "log2", "sin", "cos", "tan", "asin", "acos", "atan", "sinh",
+    "cosh", "tanh", "asinh", "acosh", "atanh", "signum", "abs", "floor", "ceil", "round", "sqrt",
+    "cbrt", "min", "max", "atan2",
+];
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+/// Guards against `f(x) = f(x)` and friends.
+const MAX_CALL_DEPTH: usize = 64;
+
+/// A value in SI base units, with the unit it is preferably shown in.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Quantity {
+    pub si: Number,
+    pub dim: Dim,
+    pub unit: Option<Unit>,
+}
+
+impl Quantity {
+    fn scalar(value: Number) -> Self {
+        Quantity {
+            si: value,
+            dim: Dim::NONE,
+            unit: None,
+        }
+    }
+
+    fn expect_scalar(&self, what: &str) -> AResult<Number> {
+        if self.dim.is_none() {
+            Ok(self.si.clone())
+        } else {
+            Err(aanyhow!("{} expects a dimensionless value", what))
+        }
+    }
+
+    fn display_unit(&self) -> Unit {
+        match &self.unit {
+            Some(unit) if unit.dim == self.dim => unit.clone(),
+            _ => units::canonical(&self.dim),
+        }
+    }
+
+    fn format_in(&self, unit: &Unit, precision: usize) -> AResult<String> {
+        let value = unit.of_si(&self.si)?.format(precision);
+        if unit.dim.is_none() {
+            Ok(value)
+        } else {
+            Ok(format!("{} {}", value, unit.symbol))
+        }
+    }
+
+    pub fn format(&self, precision: usize) -> AResult<String> {
+        self.format_in(&self.display_unit(), precision)
+    }
+}
+
+pub struct Evaluation {
+    pub display: String,
+    /// Extra `(label, value)` rows for the preview.
+    pub details: Vec<(String, String)>,
+    /// Stored into the context once the result is picked.
+    pub binding: Option<Binding>,
+}
+
+/// Evaluate one line of input, `None` if it doesn't look like a calculation at all.
+pub fn evaluate(input: &str, ctx: &CalcContext, precision: usize) -> AResult<Option<Evaluation>> {
+    let env = Env {
+        ctx,
+        locals: vec![],
+        depth: 0,
+    };
+
+    let (value, target, binding_name) = match parser::parse(input, &|n| ctx.is_bound(n))? {
+        Statement::Define { name, params, body } => {
+            context::check_name(&name)?;
+            let func = UserFn {
+                params,
+                body,
+                source: input.trim().to_string(),
+            };
+            let display = func.source.clone();
+            return Ok(Some(Evaluation {
+                details: vec![("Defines".to_string(), func.signature())],
+                display,
+                binding: Some(Binding::Func(name, func)),
+            }));
+        }
+        Statement::Assign { name, expr } => {
+            context::check_name(&name)?;
+            (env.eval(&expr)?, None, name)
+        }
+        Statement::Expr { expr, target } => {
+            // a lone `m`, `pi` or `min` is far more likely the start of a search,
+            // but a variable the user defined is worth showing
+            let lone = match &expr {
+                Expr::Unit(_) => true,
+                Expr::Ident(name) => !ctx.vars.contains_key(name),
+                _ => false,
+            };
+            if target.is_none() && lone {
+                return Ok(None);
+            }
+            (env.eval(&expr)?, target, context::ANS.to_string())
+        }
+    };
+
+    let (display_unit, base) = match target {
+        Some(Target::Unit(unit)) => {
+            if unit.dim != value.dim {
+                return Err(aanyhow!(
+                    "cannot convert {:?} to {}",
+                    value.dim,
+                    unit.symbol
+                ));
+            }
+            (unit, None)
+        }
+        Some(Target::Base(base)) => {
+            value.expect_scalar(base.label())?;
+            (value.display_unit(), Some(base))
+        }
+        None => (value.display_unit(), None),
+    };
+
+    let display = match base {
+        Some(base) => value
+            .si
+            .to_base(base)
+            .ok_or_else(|| aanyhow!("only integers can be shown in {}", base.label()))?,
+        None => value.format_in(&display_unit, precision)?,
+    };
+
+    let mut details = vec![];
+    if binding_name != context::ANS {
+        details.push(("Assigns".to_string(), binding_name.clone()));
+    }
+    if value.dim.is_none() {
+        if let Some(fraction) = value.si.fraction() {
+            details.push(("Fraction".to_string(), fraction));
+        }
+        for base in Base::ALL {
+            if let Some(digits) = value.si.to_base(base) {
+                details.push((base.label().to_string(), digits));
+            }
+        }
+    } else {
+        let canonical = units::canonical(&value.dim);
+        details.push((
+            "Canonical".to_string(),
+            value.format_in(&canonical, precision)?,
+        ));
+        if let Some(name) = value.dim.name() {
+            details.push(("Quantity".to_string(), name.to_string()));
+        }
+    }
+
+    // keep the unit that was asked for, `ans` of `5 km to mi` is in miles
+    let value = Quantity {
+        unit: Some(display_unit),
+        ..value
+    };
+
+    Ok(Some(Evaluation {
+        display,
+        details,
+        binding: Some(Binding::Var(binding_name, value)),
+    }))
+}
+
+/// A plain expression like `3 weeks + 2 d`, for callers which need the value itself.
+pub fn quantity(input: &str, ctx: &CalcContext) -> AResult<Quantity> {
+    match parser::parse(input, &|n| ctx.is_bound(n))? {
+        Statement::Expr { expr, target: None } => Env {
+            ctx,
+            locals: vec![],
+            depth: 0,
+        }
+        .eval(&expr),
+        _ => Err(aanyhow!("expected an expression: {}", input)),
+    }
+}
+
+struct Env<'a> {
+    ctx: &'a CalcContext,
+    /// Arguments of the user function being called.
+    locals: Vec<(String, Quantity)>,
+    depth: usize,
+}
+
+impl Env<'_> {
+    fn eval(&self, expr: &Expr) -> AResult<Quantity> {
+        match expr {
+            Expr::Number(n) => Ok(Quantity::scalar(n.clone())),
+            Expr::WithUnit(value, unit) => {
+                let value = self.eval(value)?.expect_scalar("a unit")?;
+                Ok(Quantity {
+                    si: unit.to_si(&value),
+                    dim: unit.dim,
+                    unit: Some(unit.clone()),
+                })
+            }
+            Expr::Unit(unit) => Ok(Quantity {
+                si: unit.to_si(&Number::int(1)),
+                dim: unit.dim,
+                unit: Some(unit.clone()),
+            }),
+            Expr::Ident(name) => self.lookup(name),
+            Expr::Neg(operand) => {
+                let q = self.eval(operand)?;
+                Ok(Quantity {
+                    si: q.si.neg(),
+                    ..q
+                })
+            }
+            Expr::BitNot(operand) => Ok(Quantity::scalar(
+                self.eval(operand)?.expect_scalar("~")?.bit_not()?,
+            )),
+            Expr::Binary(op, lhs, rhs) => binary(*op, self.eval(lhs)?, self.eval(rhs)?),
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<AResult<Vec<_>>>()?;
+                match self.ctx.funcs.get(name) {
+                    Some(func) => self.call_user(name, func, args),
+                    None => call(name, args),
+                }
+            }
+        }
+    }
+
+    fn lookup(&self, name: &str) -> AResult<Quantity> {
+        if let Some((_, value)) = self.locals.iter().find(|(n, _)| n == name) {
+            return Ok(value.clone());
+        }
+        if let Some(value) = self.ctx.vars.get(name) {
+            return Ok(value.clone());
+        }
+        CONSTANTS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| Quantity::scalar(Number::Float(*v)))
+            .ok_or_else(|| aanyhow!("unknown identifier: {}", name))
+    }
+
+    fn call_user(&self, name: &str, func: &UserFn, args: Vec<Quantity>) -> AResult<Quantity> {
+        if args.len() != func.params.len() {
+            return Err(aanyhow!("{} expects {} arguments", name, func.params.len()));
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(aanyhow!("{} recurses too deep", name));
+        }
+
+        let env = Env {
+            ctx: self.ctx,
+            locals: func.params.iter().cloned().zip(args).collect(),
+            depth: self.depth + 1,
+        };
+        env.eval(&func.body)
+    }
+}
+
+fn binary(op: char, lhs: Quantity, rhs: Quantity) -> AResult<Quantity> {
+    match op {
+        '+' | '-' | '%' => {
+            if lhs.dim != rhs.dim {
+                return Err(aanyhow!("incompatible units for {}", op));
+            }
+            let si = match op {
+                '+' => lhs.si.add(&rhs.si),
+                '-' => lhs.si.sub(&rhs.si),
+                _ => lhs.si.rem(&rhs.si)?,
+            };
+            Ok(Quantity {
+                si,
+                dim: lhs.dim,
+                unit: lhs.unit.or(rhs.unit),
+            })
+        }
+        '*' | '/' => {
+            let (si, dim) = if op == '*' {
+                (lhs.si.mul(&rhs.si), lhs.dim.mul(&rhs.dim)?)
+            } else {
+                (lhs.si.div(&rhs.si)?, lhs.dim.div(&rhs.dim)?)
+            };
+            // scaling keeps the unit, `10 km / 2` is still shown in km
+            let unit = match (lhs.dim.is_none(), rhs.dim.is_none()) {
+                (false, true) => lhs.unit,
+                (true, false) if op == '*' => rhs.unit,
+                _ => None,
+            };
+            Ok(Quantity { si, dim, unit })
+        }
+        '^' => {
+            let exp = rhs.expect_scalar("an exponent")?;
+            if lhs.dim.is_none() {
+                return Ok(Quantity::scalar(lhs.si.pow(&exp)?));
+            }
+            let exp = exp
+                .to_i64()
+                .filter(|e| e.abs() <= i8::MAX as i64)
+                .ok_or_else(|| aanyhow!("units can only be raised to integer powers"))?
+                as i8;
+            Ok(Quantity {
+                si: lhs.si.pow(&Number::int(exp as i64))?,
+                dim: lhs.dim.pow(exp)?,
+                unit: lhs.unit.map(|u| u.pow(exp)).transpose()?,
+            })
+        }
+        '|' | '&' | XOR | SHL | SHR => {
+            let what = "bitwise operators";
+            let (lhs, rhs) = (lhs.expect_scalar(what)?, rhs.expect_scalar(what)?);
+            let value = match op {
+                '|' => lhs.bitwise(|a, b| a | b, &rhs)?,
+                '&' => lhs.bitwise(|a, b| a & b, &rhs)?,
+                XOR => lhs.bitwise(|a, b| a ^ b, &rhs)?,
+                SHL => lhs.shift(&rhs, true)?,
+                _ => lhs.shift(&rhs, false)?,
+            };
+            Ok(Quantity::scalar(value))
+        }
+        _ => Err(aanyhow!("unknown operator: {}", op)),
+    }
+}
+
+fn call(name: &str, args: Vec<Quantity>) -> AResult<Quantity> {
+    let one = |args: &[Quantity]| match args {
+        [q] => Ok(q.clone()),
+        _ => Err(aanyhow!("{} expects one argument", name)),
+    };
+
+    let scalar_fn: Option<fn(f64) -> f64> = match name {
+        "exp" => Some(f64::exp),
+        "ln" => Some(f64::ln),
+        "log" | "log10" => Some(f64::log10),
+        "log2" => Some(f64::log2),
+        "sin" => Some(f64::sin),
+        "cos" => Some(f64::cos),
+        "tan" => Some(f64::tan),
+        "asin" => Some(f64::asin),
+        "acos" => Some(f64::acos),
+        "atan" => Some(f64::atan),
+        "sinh" => Some(f64::sinh),
+        "cosh" => Some(f64::cosh),
+        "tanh" => Some(f64::tanh),
+        "asinh" => Some(f64::asinh),
+        "acosh" => Some(f64::acosh),
+        "atanh" => Some(f64::atanh),
+        "signum" => Some(f64::signum),
+        _ => None,
+    };
+    if let Some(f) = scalar_fn {
+        return Ok(Quantity::scalar(
+            one(&args)?.expect_scalar(name)?.map_f64(f),
+        ));
+    }
+
+    match name {
+        "abs" | "floor" | "ceil" | "round" => {
+            let q = one(&args)?;
+            let unit = q.display_unit();
+            // round in the unit the value is shown in, `round(2.6 km)` is 3 km
+            let shown = unit.of_si(&q.si)?.integral(name);
+            Ok(Quantity {
+                si: unit.to_si(&shown),
+                ..q
+            })
+        }
+        "sqrt" | "cbrt" => {
+            let q = one(&args)?;
+            let n = if name == "sqrt" { 2 } else { 3 };
+            let dim = q
+                .dim
+                .root(n)
+                .ok_or_else(|| aanyhow!("{} of {:?} has no unit", name, q.dim))?;
+            Ok(Quantity {
+                si: q.si.root(n as u32),
+                dim,
+                unit: None,
+            })
+        }
+        "min" | "max" => {
+            let mut iter = args.into_iter();
+            let first = iter
+                .next()
+                .ok_or_else(|| aanyhow!("{} expects arguments", name))?;
+            iter.try_fold(first, |acc, q| {
+                if acc.dim != q.dim {
+                    return Err(aanyhow!("incompatible units for {}", name));
+                }
+                let ordering = q.si.compare(&acc.si);
+                let pick_q = if name == "min" {
+                    ordering == Some(Ordering::Less)
+                } else {
+                    ordering == Some(Ordering::Greater)
+                };
+                Ok(if pick_q { q } else { acc })
+            })
+        }
+        "atan2" => match args.as_slice() {
+            [y, x] if y.dim == x.dim => Ok(Quantity::scalar(Number::Float(
+                y.si.to_f64().atan2(x.si.to_f64()),
+            ))),
+            _ => Err(aanyhow!("atan2 expects two values of the same unit")),
+        },
+        _ => Err(aanyhow!("unknown function: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::context::CalcContext;
+    use super::{evaluate, Evaluation, DEFAULT_PRECISION};
+    use chin_tools::AResult;
+
+    fn eval(input: &str, ctx: &CalcContext) -> AResult<Option<Evaluation>> {
+        evaluate(input, ctx, DEFAULT_PRECISION)
+    }
+
+    fn display(input: &str) -> String {
+        eval(input, &CalcContext::default())
+            .unwrap()
+            .unwrap()
+            .display
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(display("1 + 2 * 3"), "7");
+        assert_eq!(display("5 km to mi"), "3.10685596119 mi");
+        assert_eq!(display("72 F in C"), "22.2222222222 C");
+        assert_eq!(display("-40 C to F"), "-40 F");
+        assert_eq!(display("3 GiB / 20 MB/s"), "161.0612736 s");
+        assert_eq!(display("1.5h + 20min"), "1.83333333333 h");
+        assert_eq!(display("1.5h + 20min to min"), "110 min");
+        assert_eq!(display("60 km/h to m/s"), "16.6666666667 m/s");
+        assert_eq!(display("2 m * 3 m"), "6 m²");
+        assert_eq!(display("1 atm to kPa"), "101.325 kPa");
+        assert_eq!(display("1 kWh to MJ"), "3.6 MJ");
+        assert_eq!(display("-2^2"), "-4");
+        assert_eq!(display("(3 m/s)^2"), "9 m²/s²");
+        assert_eq!(display("(2 m^2)^3"), "8 m⁶");
+        assert!(eval("(5 m^9)^20", &CalcContext::default()).is_err());
+    }
+
+    #[test]
+    fn test_exact() {
+        assert_eq!(display("0.1 + 0.2"), "0.3");
+        assert_eq!(display("2^64"), "18446744073709551616");
+        assert_eq!(display("2^64 + 1 - 2^64"), "1");
+        assert_eq!(display("1/3"), "0.333333333333");
+        assert_eq!(display("2/3 * 3"), "2");
+        assert_eq!(display("sqrt(16/9) * 3"), "4");
+        assert_eq!(display("1e-9 * 3"), "3e-9");
+        assert_eq!(display("1/7 * 1e20"), "1.42857142857e19");
+        assert_eq!(display("1e1000000"), "inf");
+
+        let details = eval("1/4", &CalcContext::default())
+            .unwrap()
+            .unwrap()
+            .details;
+        assert!(details.contains(&("Fraction".to_string(), "1/4".to_string())));
+
+        let third = evaluate("1/3", &CalcContext::default(), 4)
+            .unwrap()
+            .unwrap();
+        assert_eq!(third.display, "0.3333");
+    }
+
+    #[test]
+    fn test_programmer() {
+        assert_eq!(display("0xff | 0b1010"), "255");
+        assert_eq!(display("0xff & 0o17"), "15");
+        assert_eq!(display("6 xor 3"), "5");
+        assert_eq!(display("1 << 70"), "1180591620717411303424");
+        assert_eq!(display("0x100 >> 4"), "16");
+        assert_eq!(display("~0"), "-1");
+        assert_eq!(display("1 + 2 | 4"), "7");
+        assert_eq!(display("255 to hex"), "0xff");
+        assert_eq!(display("-10 in bin"), "-0b1010");
+        assert_eq!(display("0b1111_0000 to oct"), "0o360");
+
+        let details = eval("255", &CalcContext::default())
+            .unwrap()
+            .unwrap()
+            .details;
+        assert!(details.contains(&("Hex".to_string(), "0xff".to_string())));
+        assert!(details.contains(&("Binary".to_string(), "0b11111111".to_string())));
+
+        let ctx = CalcContext::default();
+        assert!(eval("1.5 to hex", &ctx).is_err());
+        assert!(eval("1.5 | 1", &ctx).is_err());
+        assert!(eval("1 / 0", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_not_calculation() {
+        let ctx = CalcContext::default();
+        assert!(eval("m", &ctx).unwrap().is_none());
+        assert!(eval("firefox", &ctx).unwrap().is_none());
+        assert!(eval("5 km to kg", &ctx).is_err());
+        assert!(eval("sin = 4", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_context() {
+        let mut ctx = CalcContext::default();
+        let mut run = |input: &str| {
+            let evaluation = eval(input, &ctx).unwrap().unwrap();
+            ctx.bind(evaluation.binding.unwrap());
+            evaluation.display
+        };
+
+        assert_eq!(run("5 km to mi"), "3.10685596119 mi");
+        assert_eq!(run("ans * 2"), "6.21371192237 mi");
+        assert_eq!(run("r = 4.2"), "4.2");
+        assert_eq!(run("f(x) = x^2 + 1"), "f(x) = x^2 + 1");
+        assert_eq!(run("f(r) - 1"), "17.64");
+        assert_eq!(run("t = 2 h"), "2 h");
+        // `t` is now the variable, not tonnes
+        assert_eq!(run("t to min"), "120 min");
+        assert_eq!(run("area(w, h) = w * h"), "area(w, h) = w * h");
+        assert_eq!(run("area(2 m, 3 m)"), "6 m²");
+        assert!(eval("g(x) = g(x)", &ctx)
+            .map(|e| ctx.bind(e.unwrap().binding.unwrap()))
+            .is_ok());
+        assert!(eval("g(1)", &ctx).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use crate::config::CalcConfig;
+use crate::dispatcher::CONNECTION;
+use crate::plugins::history::{HistoryDb, HistoryItem};
+use crate::plugins::{Plugin, PluginResult};
+use crate::userinput::UserInput;
+
+use crate::util::score_utils;
+use arboard::Clipboard;
+use arc_swap::ArcSwap;
+use chin_tools::{AResult, EResult, SharedStr};
+use chrono::Utc;
+use context::{CalcContext, ContextDb};
+use eval::Evaluation;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use super::history::HistoryCache;
+
+mod context;
+mod datetime;
+mod eval;
+mod num;
+mod parser;
+mod units;
+
+pub const TYPE_ID: &str = "calc";
+
+#[derive(Clone)]
+pub struct CalcReq {}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct CalcResult {
+    pub formula: SharedStr,
+    pub result: SharedStr,
+    /// `(label, value)` pairs shown under the result, e.g. the value in SI units.
+    #[serde(default)]
+    pub details: Vec<(SharedStr, SharedStr)>,
+    /// Variables and functions defined so far, as `(name, value)`.
+    #[serde(skip)]
+    pub variables: Vec<(SharedStr, SharedStr)>,
+}
+
+impl PluginResult for CalcResult {
+    fn icon_name(&self) -> &str {
+        "calc"
+    }
+
+    fn name(&self) -> &str {
+        self.formula.as_str()
+    }
+
+    fn extra(&self) -> Option<&str> {
+        None
+    }
+
+    fn on_enter(&self) {
+        copy_to_clipboard(self.result.as_str());
+    }
+
+    fn on_alt_enter(&self) {
+        copy_to_clipboard(&format!("{} = {}", self.formula, self.result));
+    }
+
+    fn get_type_id(&self) -> &'static str {
+        &TYPE_ID
+    }
+
+    fn get_id(&self) -> &str {
+        self.formula.as_str()
+    }
+
+    fn to_enum(self) -> super::PluginResultEnum {
+        super::PluginResultEnum::Calc(self)
+    }
+}
+
+fn copy_to_clipboard(text: &str) {
+    if let Err(err) = Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        error!("unable to copy {}: {}", text, err);
+    }
+}
+
+pub struct CalcPlugin {
+    history: HistoryCache<CalcResult>,
+    context: ArcSwap<CalcContext>,
+    precision: usize,
+}
+
+impl CalcPlugin {
+    pub fn new(config: Option<&CalcConfig>) -> AResult<Self> {
+        info!("Creating Calc Plugin");
+
+        let (histories, context) = CONNECTION.with_borrow(|e| -> AResult<_> {
+            let histories: Vec<HistoryItem<CalcResult>> =
+                HistoryDb::new(e.as_ref()).fetch_histories(TYPE_ID)?;
+            let context_db = ContextDb::new(e.as_ref());
+            context_db.try_create_table()?;
+            Ok((histories, context_db.load()?))
+        })?;
+
+        Ok(CalcPlugin {
+            history: HistoryCache::new(histories),
+            context: ArcSwap::from_pointee(context),
+            precision: config
+                .and_then(|c| c.precision)
+                .unwrap_or(eval::DEFAULT_PRECISION),
+        })
+    }
+
+    fn evaluate(&self, input: &str, context: &CalcContext) -> AResult<Option<Evaluation>> {
+        match datetime::evaluate(input, context, self.precision, Utc::now())? {
+            Some(evaluation) => Ok(Some(evaluation)),
+            None => eval::evaluate(input, context, self.precision),
+        }
+    }
+
+    /// Store what a picked result defines, `ans` for plain calculations.
+    fn commit(&self, formula: &str, context_db: ContextDb) -> EResult {
+        let mut context = self.context.load().as_ref().clone();
+        if let Some(binding) = self.evaluate(formula, &context)?.and_then(|e| e.binding) {
+            context_db.store(&binding)?;
+            context.bind(binding);
+            self.context.store(Arc::new(context));
+        }
+
+        Ok(())
+    }
+}
+
+impl Plugin for CalcPlugin {
+    type R = CalcResult;
+
+    type T = CalcReq;
+
+    fn handle_input(&self, user_input: &UserInput) -> AResult<Vec<(CalcResult, i32)>> {
+        let mut result = Vec::with_capacity(1);
+        if user_input.input.is_empty() {
+            return Ok(result);
+        }
+
+        let context = self.context.load();
+        if let Ok(Some(evaluation)) = self.evaluate(user_input.input.as_str(), &context) {
+            result.push((
+                CalcResult {
+                    formula: user_input.input.clone(),
+                    result: evaluation.display.into(),
+                    details: evaluation
+                        .details
+                        .into_iter()
+                        .map(|(k, v)| (k.into(), v.into()))
+                        .collect(),
+                    variables: context
+                        .table(self.precision)
+                        .into_iter()
+                        .map(|(k, v)| (k.into(), v.into()))
+                        .collect(),
+                },
+                score_utils::highest(0),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    fn get_type_id(&self) -> &'static str {
+        &TYPE_ID
+    }
+
+    fn add_history(&self, item: HistoryItem<Self::R>) -> EResult {
+        CONNECTION.with_borrow(|conn| {
+            if let Err(err) = self.commit(item.body.formula.as_str(), ContextDb::new(conn.as_ref()))
+            {
+                error!("unable to store calc context: {}", err);
+            }
+            self.history
+                .add_history(item, HistoryDb::new(conn.as_ref()))
+        })
+    }
+
+    /// Past computations, previewed along with the current variables.
+    fn get_history<'a>(&self) -> Vec<HistoryItem<Self::R>> {
+        let variables: Vec<(SharedStr, SharedStr)> = self
+            .context
+            .load()
+            .table(self.precision)
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+
+        self.history
+            .histories
+            .load()
+            .values()
+            .map(|item| HistoryItem {
+                body: CalcResult {
+                    variables: variables.clone(),
+                    ..item.body.clone()
+                },
+                ..item.clone()
+            })
+            .collect()
+    }
+}
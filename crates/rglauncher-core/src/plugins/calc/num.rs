@@ -0,0 +1,404 @@
+use std::cmp::Ordering;
+
+use chin_tools::{aanyhow, AResult};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Integers this long are shown in scientific notation instead of digit by digit.
+const MAX_INTEGER_DIGITS: usize = 100;
+
+/// Exact results above this many bits fall back to floats, `10^10^10` stays cheap.
+const MAX_EXACT_BITS: u64 = 1 << 16;
+
+/// A rational as long as every step is exact, a float after `sqrt(2)`, `sin(1)`...
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+    Exact(BigRational),
+    Float(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Base {
+    Bin,
+    Oct,
+    Dec,
+    Hex,
+}
+
+impl Base {
+    pub const ALL: [Base; 4] = [Base::Dec, Base::Hex, Base::Oct, Base::Bin];
+
+    pub fn from_name(name: &str) -> Option<Base> {
+        match name {
+            "bin" | "binary" => Some(Base::Bin),
+            "oct" | "octal" => Some(Base::Oct),
+            "dec" | "decimal" => Some(Base::Dec),
+            "hex" | "hexadecimal" => Some(Base::Hex),
+            _ => None,
+        }
+    }
+
+    pub fn radix(&self) -> u32 {
+        match self {
+            Base::Bin => 2,
+            Base::Oct => 8,
+            Base::Dec => 10,
+            Base::Hex => 16,
+        }
+    }
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Base::Bin => "0b",
+            Base::Oct => "0o",
+            Base::Dec => "",
+            Base::Hex => "0x",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Base::Bin => "Binary",
+            Base::Oct => "Octal",
+            Base::Dec => "Decimal",
+            Base::Hex => "Hex",
+        }
+    }
+}
+
+impl Number {
+    pub fn int(value: i64) -> Self {
+        Number::Exact(BigRational::from_integer(value.into()))
+    }
+
+    /// Goes through the shortest decimal form, so `0.3048` becomes `3048/10000`.
+    pub fn from_f64(value: f64) -> Self {
+        if !value.is_finite() {
+            return Number::Float(value);
+        }
+        Number::parse_decimal(&format!("{:e}", value)).unwrap_or(Number::Float(value))
+    }
+
+    /// `12`, `1.5`, `.5`, `2.5e-3`, exactly.
+    pub fn parse_decimal(literal: &str) -> Option<Self> {
+        let (mantissa, exp) = match literal.split_once(['e', 'E']) {
+            Some((mantissa, exp)) => (mantissa, exp.parse::<i32>().ok()?),
+            None => (literal, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int.is_empty() && frac.is_empty() {
+            return None;
+        }
+
+        let digits: BigInt = format!("{}{}", int, frac).parse().ok()?;
+        let exp = exp.checked_sub(frac.len() as i32)?;
+        // each power of ten takes about 3.32 bits
+        if exp.unsigned_abs() as u64 * 10 / 3 > MAX_EXACT_BITS {
+            return literal.parse().ok().map(Number::Float);
+        }
+        Some(Number::Exact(
+            BigRational::from_integer(digits) * pow10(exp),
+        ))
+    }
+
+    pub fn parse_radix(digits: &str, radix: u32) -> Option<Self> {
+        BigInt::parse_bytes(digits.as_bytes(), radix)
+            .map(|int| Number::Exact(BigRational::from_integer(int)))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Exact(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Float(f) => *f,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Exact(r) => r.is_zero(),
+            Number::Float(f) => *f == 0.,
+        }
+    }
+
+    pub fn to_integer(&self) -> Option<BigInt> {
+        match self {
+            Number::Exact(r) if r.is_integer() => Some(r.to_integer()),
+            _ => None,
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Number::Exact(r) if r.is_integer() => r.to_integer().to_i64(),
+            Number::Float(f) if f.fract() == 0. && f.abs() < i64::MAX as f64 => Some(*f as i64),
+            _ => None,
+        }
+    }
+
+    fn float_op(&self, other: &Number, op: fn(f64, f64) -> f64) -> Number {
+        Number::Float(op(self.to_f64(), other.to_f64()))
+    }
+
+    pub fn add(&self, other: &Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a + b),
+            _ => self.float_op(other, |a, b| a + b),
+        }
+    }
+
+    pub fn sub(&self, other: &Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a - b),
+            _ => self.float_op(other, |a, b| a - b),
+        }
+    }
+
+    pub fn mul(&self, other: &Number) -> Number {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a * b),
+            _ => self.float_op(other, |a, b| a * b),
+        }
+    }
+
+    pub fn div(&self, other: &Number) -> AResult<Number> {
+        if other.is_zero() {
+            return Err(aanyhow!("division by zero"));
+        }
+        Ok(match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a / b),
+            _ => self.float_op(other, |a, b| a / b),
+        })
+    }
+
+    pub fn rem(&self, other: &Number) -> AResult<Number> {
+        if other.is_zero() {
+            return Err(aanyhow!("division by zero"));
+        }
+        Ok(match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Number::Exact(a % b),
+            _ => self.float_op(other, |a, b| a % b),
+        })
+    }
+
+    pub fn neg(&self) -> Number {
+        match self {
+            Number::Exact(r) => Number::Exact(-r),
+            Number::Float(f) => Number::Float(-f),
+        }
+    }
+
+    pub fn pow(&self, exp: &Number) -> AResult<Number> {
+        if let (Number::Exact(base), Some(exp)) = (self, exp.to_integer()) {
+            let bits = base.numer().bits().max(base.denom().bits());
+            let exact = exp
+                .abs()
+                .to_u64()
+                .filter(|e| bits.saturating_mul(*e) <= MAX_EXACT_BITS)
+                .and_then(|e| i32::try_from(e).ok());
+            if let Some(e) = exact {
+                if exp.is_negative() && base.is_zero() {
+                    return Err(aanyhow!("division by zero"));
+                }
+                let e = if exp.is_negative() { -e } else { e };
+                return Ok(Number::Exact(num_traits::Pow::pow(base, e)));
+            }
+        }
+        Ok(self.float_op(exp, f64::powf))
+    }
+
+    pub fn map_f64(&self, f: fn(f64) -> f64) -> Number {
+        Number::Float(f(self.to_f64()))
+    }
+
+    /// `abs`, `floor`, `ceil` and `round`, exact when the value is.
+    pub fn integral(&self, name: &str) -> Number {
+        match self {
+            Number::Exact(r) => Number::Exact(match name {
+                "abs" => r.abs(),
+                "floor" => r.floor(),
+                "ceil" => r.ceil(),
+                _ => r.round(),
+            }),
+            Number::Float(f) => Number::Float(match name {
+                "abs" => f.abs(),
+                "floor" => f.floor(),
+                "ceil" => f.ceil(),
+                _ => f.round(),
+            }),
+        }
+    }
+
+    /// Exact for perfect powers, `sqrt(16/9)` is `4/3`.
+    pub fn root(&self, n: u32) -> Number {
+        if let Number::Exact(r) = self {
+            if !r.is_negative() || n % 2 == 1 {
+                let (numer, denom) = (r.numer().nth_root(n), r.denom().nth_root(n));
+                let candidate = BigRational::new(numer, denom);
+                if num_traits::Pow::pow(&candidate, n) == *r {
+                    return Number::Exact(candidate);
+                }
+            }
+        }
+        let value = self.to_f64();
+        Number::Float(match n {
+            2 => value.sqrt(),
+            3 => value.cbrt(),
+            _ => value.powf(1. / n as f64),
+        })
+    }
+
+    pub fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Exact(a), Number::Exact(b)) => Some(a.cmp(b)),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+
+    pub fn bitwise(&self, op: fn(&BigInt, &BigInt) -> BigInt, other: &Number) -> AResult<Number> {
+        match (self.to_integer(), other.to_integer()) {
+            (Some(a), Some(b)) => Ok(Number::Exact(BigRational::from_integer(op(&a, &b)))),
+            _ => Err(aanyhow!("bitwise operators expect integers")),
+        }
+    }
+
+    pub fn shift(&self, by: &Number, left: bool) -> AResult<Number> {
+        let value = self
+            .to_integer()
+            .ok_or_else(|| aanyhow!("shifts expect integers"))?;
+        let by = by
+            .to_i64()
+            .filter(|by| (0..=MAX_EXACT_BITS as i64).contains(by))
+            .ok_or_else(|| aanyhow!("invalid shift amount"))? as usize;
+        let value = if left { value << by } else { value >> by };
+        Ok(Number::Exact(BigRational::from_integer(value)))
+    }
+
+    pub fn bit_not(&self) -> AResult<Number> {
+        match self.to_integer() {
+            Some(int) => Ok(Number::Exact(BigRational::from_integer(!int))),
+            None => Err(aanyhow!("~ expects an integer")),
+        }
+    }
+
+    /// `3/4`, only for exact values which aren't integers.
+    pub fn fraction(&self) -> Option<String> {
+        match self {
+            Number::Exact(r) if !r.is_integer() => Some(r.to_string()),
+            _ => None,
+        }
+    }
+
+    /// `-0xff`, only for integers.
+    pub fn to_base(&self, base: Base) -> Option<String> {
+        let int = self.to_integer()?;
+        let sign = if int.is_negative() { "-" } else { "" };
+        Some(format!(
+            "{}{}{}",
+            sign,
+            base.prefix(),
+            int.abs().to_str_radix(base.radix())
+        ))
+    }
+
+    /// Rounded to `precision` significant digits, integers are kept whole.
+    pub fn format(&self, precision: usize) -> String {
+        let r = match self {
+            Number::Exact(r) => r.clone(),
+            Number::Float(f) if !f.is_finite() => return f.to_string(),
+            Number::Float(f) => match Number::from_f64(*f) {
+                Number::Exact(r) => r,
+                Number::Float(f) => return f.to_string(),
+            },
+        };
+
+        if r.is_integer() {
+            let digits = r.numer().to_string();
+            if digits.trim_start_matches('-').len() <= MAX_INTEGER_DIGITS {
+                return digits;
+            }
+        }
+        if r.is_zero() {
+            return "0".to_string();
+        }
+
+        let precision = precision.max(1);
+        let sign = if r.is_negative() { "-" } else { "" };
+        let abs = r.abs();
+
+        // decimal exponent of the leading digit
+        let mut exp = abs.numer().to_string().len() as i32 - abs.denom().to_string().len() as i32;
+        if abs < pow10(exp) {
+            exp -= 1;
+        }
+        let mut scaled = (abs * pow10(precision as i32 - 1 - exp))
+            .round()
+            .to_integer();
+        if scaled.to_string().len() > precision {
+            scaled /= 10;
+            exp += 1;
+        }
+        let digits = scaled.to_string();
+
+        if exp < -6 || exp >= precision as i32 {
+            let (lead, rest) = digits.split_at(1);
+            let mantissa = trim_zeros(format!("{}.{}", lead, rest));
+            return format!("{}{}e{}", sign, mantissa, exp);
+        }
+
+        let fixed = if exp >= 0 {
+            let (int, frac) = digits.split_at(exp as usize + 1);
+            format!("{}.{}", int, frac)
+        } else {
+            format!("0.{}{}", "0".repeat((-exp - 1) as usize), digits)
+        };
+        format!("{}{}", sign, trim_zeros(fixed))
+    }
+}
+
+fn pow10(exp: i32) -> BigRational {
+    let ten = BigRational::from_integer(10.into());
+    if exp >= 0 {
+        num_traits::Pow::pow(ten, exp as u32)
+    } else {
+        BigRational::one() / num_traits::Pow::pow(ten, exp.unsigned_abs())
+    }
+}
+
+fn trim_zeros(s: String) -> String {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s
+    }
+}
+
+/// Exact values are stored as `p/q` strings, floats as plain numbers.
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Number::Exact(r) => serializer.serialize_str(&r.to_string()),
+            Number::Float(f) => serializer.serialize_f64(*f),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Exact(String),
+            Float(f64),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Exact(s) => s
+                .parse()
+                .map(Number::Exact)
+                .map_err(serde::de::Error::custom),
+            Repr::Float(f) => Ok(Number::Float(f)),
+        }
+    }
+}
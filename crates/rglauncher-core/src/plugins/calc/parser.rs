@@ -0,0 +1,524 @@
+use chin_tools::{aanyhow, AResult};
+
+use super::num::{Base, Number};
+use super::units::{self, Unit};
+
+pub const XOR: char = '⊻';
+pub const SHL: char = '«';
+pub const SHR: char = '»';
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Number),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+    Arrow,
+    Assign,
+}
+
+fn tokenize(input: &str) -> AResult<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => {
+                i += 1;
+            }
+            // `0xff`, `0o17`, `0b1010`, but `0 b` is still zero bits
+            '0' if matches!(chars.get(i + 1), Some('x' | 'o' | 'b'))
+                && chars
+                    .get(i + 2)
+                    .is_some_and(|c| c.is_digit(radix_of(chars[i + 1]))) =>
+            {
+                let radix = radix_of(chars[i + 1]);
+                let start = i + 2;
+                i = start;
+                while i < chars.len() && (chars[i].is_digit(radix) || chars[i] == '_') {
+                    i += 1;
+                }
+                let digits: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+                let number = Number::parse_radix(&digits, radix)
+                    .ok_or_else(|| aanyhow!("invalid number: {}", digits))?;
+                tokens.push(Token::Number(number));
+            }
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // scientific notation, but keep `2e` or `3 em` as identifiers
+                if i + 1 < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let digit_at = if chars[i + 1] == '-' || chars[i + 1] == '+' {
+                        i + 2
+                    } else {
+                        i + 1
+                    };
+                    if digit_at < chars.len() && chars[digit_at].is_ascii_digit() {
+                        i = digit_at;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let literal: String = chars[start..i].iter().collect();
+                let number = Number::parse_decimal(&literal)
+                    .ok_or_else(|| aanyhow!("invalid number: {}", literal))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '°' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '°')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            '-' if chars.get(i + 1) == Some(&'>') => {
+                tokens.push(Token::Arrow);
+                i += 2;
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                tokens.push(Token::Op('^'));
+                i += 2;
+            }
+            '<' if chars.get(i + 1) == Some(&'<') => {
+                tokens.push(Token::Op(SHL));
+                i += 2;
+            }
+            '>' if chars.get(i + 1) == Some(&'>') => {
+                tokens.push(Token::Op(SHR));
+                i += 2;
+            }
+            '+' | '-' | '*' | '/' | '^' | '%' | '|' | '&' | '~' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '×' | '·' => {
+                tokens.push(Token::Op('*'));
+                i += 1;
+            }
+            '÷' => {
+                tokens.push(Token::Op('/'));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '=' => {
+                tokens.push(Token::Assign);
+                i += 1;
+            }
+            _ => return Err(aanyhow!("unexpected character: {}", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn radix_of(c: char) -> u32 {
+    match c {
+        'x' => 16,
+        'o' => 8,
+        _ => 2,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(Number),
+    /// A value followed by its unit, `5 km`.
+    WithUnit(Box<Expr>, Unit),
+    Unit(Unit),
+    Ident(String),
+    Neg(Box<Expr>),
+    BitNot(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    Unit(Unit),
+    /// `to hex`, `in bin`...
+    Base(Base),
+}
+
+/// One line of calculator input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    /// `expr [to target]`
+    Expr { expr: Expr, target: Option<Target> },
+    /// `name = expr`
+    Assign { name: String, expr: Expr },
+    /// `name(params) = body`
+    Define {
+        name: String,
+        params: Vec<String>,
+        body: Expr,
+    },
+}
+
+pub const CONVERSION_KEYWORDS: &[&str] = &["to", "in", "as"];
+
+pub const XOR_KEYWORD: &str = "xor";
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Names of user variables and parameters, these shadow units: `t = 5` then `t * 2`.
+    bound: &'a dyn Fn(&str) -> bool,
+}
+
+pub fn parse(input: &str, bound: &dyn Fn(&str) -> bool) -> AResult<Statement> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        bound,
+    };
+    let statement = parser.statement()?;
+    parser.finish()?;
+    Ok(statement)
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> AResult<()> {
+        match self.next() {
+            Some(t) if t == expected => Ok(()),
+            t => Err(aanyhow!("expected {:?}, got {:?}", expected, t)),
+        }
+    }
+
+    fn at_conversion(&self) -> bool {
+        match self.peek() {
+            Some(Token::Arrow) => true,
+            Some(Token::Ident(word)) => CONVERSION_KEYWORDS.contains(&word.as_str()),
+            _ => false,
+        }
+    }
+
+    fn finish(&self) -> AResult<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(t) => Err(aanyhow!("unexpected token: {:?}", t)),
+        }
+    }
+
+    fn statement(&mut self) -> AResult<Statement> {
+        if let Some(statement) = self.definition()? {
+            return Ok(statement);
+        }
+
+        let expr = self.expr()?;
+        let target = if self.at_conversion() {
+            self.next();
+            Some(self.target()?)
+        } else {
+            None
+        };
+        Ok(Statement::Expr { expr, target })
+    }
+
+    /// `name = expr` or `name(a, b) = body`, decided by looking for the `=` up front.
+    fn definition(&mut self) -> AResult<Option<Statement>> {
+        let Some(Token::Ident(name)) = self.peek().cloned() else {
+            return Ok(None);
+        };
+        let Some(assign_at) = self.tokens.iter().position(|t| *t == Token::Assign) else {
+            return Ok(None);
+        };
+
+        if assign_at == 1 {
+            self.pos = 2;
+            let expr = self.expr()?;
+            return Ok(Some(Statement::Assign { name, expr }));
+        }
+
+        let mut params = vec![];
+        let mut tokens = self.tokens[1..assign_at].iter();
+        if tokens.next() != Some(&Token::LParen) || tokens.next_back() != Some(&Token::RParen) {
+            return Err(aanyhow!("expected `name = value` or `name(x) = body`"));
+        }
+        for (i, token) in tokens.enumerate() {
+            match token {
+                Token::Ident(param) if i % 2 == 0 => params.push(param.clone()),
+                Token::Comma if i % 2 == 1 => {}
+                t => return Err(aanyhow!("unexpected token in parameters: {:?}", t)),
+            }
+        }
+
+        // parameters only have to be known while parsing the body
+        let outer = self.bound;
+        let bound = |n: &str| params.iter().any(|p| p == n) || outer(n);
+        let mut body_parser = Parser {
+            tokens: self.tokens[assign_at + 1..].to_vec(),
+            pos: 0,
+            bound: &bound,
+        };
+        let body = body_parser.expr()?;
+        body_parser.finish()?;
+        self.pos = self.tokens.len();
+
+        Ok(Some(Statement::Define { name, params, body }))
+    }
+
+    fn target(&mut self) -> AResult<Target> {
+        if let Some(Token::Ident(name)) = self.peek() {
+            if let Some(base) = Base::from_name(name) {
+                self.next();
+                return Ok(Target::Base(base));
+            }
+        }
+        self.unit_expr()?
+            .map(Target::Unit)
+            .ok_or_else(|| aanyhow!("expected a unit after conversion"))
+    }
+
+    /// Bitwise operators bind looser than arithmetic: `1 + 2 | 4` is `(1 + 2) | 4`.
+    fn expr(&mut self) -> AResult<Expr> {
+        let mut lhs = self.bit_xor()?;
+        while let Some(Token::Op('|')) = self.peek() {
+            self.next();
+            let rhs = self.bit_xor()?;
+            lhs = Expr::Binary('|', lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn bit_xor(&mut self) -> AResult<Expr> {
+        let mut lhs = self.bit_and()?;
+        while matches!(self.peek(), Some(Token::Ident(word)) if word == XOR_KEYWORD) {
+            self.next();
+            let rhs = self.bit_and()?;
+            lhs = Expr::Binary(XOR, lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn bit_and(&mut self) -> AResult<Expr> {
+        let mut lhs = self.shift()?;
+        while let Some(Token::Op('&')) = self.peek() {
+            self.next();
+            let rhs = self.shift()?;
+            lhs = Expr::Binary('&', lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn shift(&mut self) -> AResult<Expr> {
+        let mut lhs = self.additive()?;
+        while let Some(Token::Op(op @ (SHL | SHR))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.additive()?;
+            lhs = Expr::Binary(op, lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn additive(&mut self) -> AResult<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.term()?;
+            lhs = Expr::Binary(op, lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> AResult<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> AResult<Expr> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.next();
+                match self.unary()? {
+                    // `-40 C` is minus forty degrees, not the negated absolute temperature
+                    Expr::WithUnit(value, unit) => match *value {
+                        Expr::Number(n) => Ok(Expr::WithUnit(Expr::Number(n.neg()).into(), unit)),
+                        value => Ok(Expr::Neg(Expr::WithUnit(value.into(), unit).into())),
+                    },
+                    operand => Ok(Expr::Neg(operand.into())),
+                }
+            }
+            Some(Token::Op('+')) => {
+                self.next();
+                self.unary()
+            }
+            Some(Token::Op('~')) => {
+                self.next();
+                Ok(Expr::BitNot(self.unary()?.into()))
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> AResult<Expr> {
+        let base = self.postfix()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.next();
+            // right associative and binds tighter than a leading minus: -2^2 = -4
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary('^', base.into(), exponent.into()));
+        }
+        Ok(base)
+    }
+
+    fn postfix(&mut self) -> AResult<Expr> {
+        let primary = self.primary()?;
+        match primary {
+            Expr::Number(_) | Expr::Call(..) => match self.unit_expr()? {
+                Some(unit) => Ok(Expr::WithUnit(primary.into(), unit)),
+                None => Ok(primary),
+            },
+            _ => Ok(primary),
+        }
+    }
+
+    fn primary(&mut self) -> AResult<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                if let Some(Token::LParen) = self.peek() {
+                    self.next();
+                    let mut args = vec![];
+                    if let Some(Token::RParen) = self.peek() {
+                        self.next();
+                    } else {
+                        loop {
+                            args.push(self.expr()?);
+                            match self.next() {
+                                Some(Token::Comma) => continue,
+                                Some(Token::RParen) => break,
+                                t => return Err(aanyhow!("unexpected token in call: {:?}", t)),
+                            }
+                        }
+                    }
+                    Ok(Expr::Call(name, args))
+                } else if let Some(unit) = self.unit_at_ident(&name)? {
+                    Ok(Expr::Unit(unit))
+                } else {
+                    Ok(Expr::Ident(name))
+                }
+            }
+            t => Err(aanyhow!("unexpected token: {:?}", t)),
+        }
+    }
+
+    /// Continue a unit that started with `name`, which has already been consumed.
+    fn unit_at_ident(&mut self, name: &str) -> AResult<Option<Unit>> {
+        if super::eval::is_constant(name) || (self.bound)(name) {
+            return Ok(None);
+        }
+        match units::lookup(name) {
+            Some(unit) => {
+                let first = self.unit_power(unit)?;
+                Ok(Some(self.unit_tail(first)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// `unit (('*' | '/') unit)*`, only consumed while the operand is a unit as well,
+    /// so `3 GiB / 20 MB/s` is read as `(3 GiB) / (20 MB/s)`.
+    fn unit_expr(&mut self) -> AResult<Option<Unit>> {
+        let unit = match self.peek() {
+            Some(Token::Ident(name))
+                if !CONVERSION_KEYWORDS.contains(&name.as_str()) && !(self.bound)(name) =>
+            {
+                units::lookup(name)
+            }
+            _ => None,
+        };
+        match unit {
+            Some(unit) => {
+                self.next();
+                let first = self.unit_power(unit)?;
+                Ok(Some(self.unit_tail(first)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn unit_tail(&mut self, mut unit: Unit) -> AResult<Unit> {
+        loop {
+            let op = match (self.peek(), self.peek_at(1)) {
+                (Some(Token::Op(op @ ('*' | '/'))), Some(Token::Ident(name)))
+                    if !(self.bound)(name) && units::lookup(name).is_some() =>
+                {
+                    *op
+                }
+                _ => return Ok(unit),
+            };
+            self.next();
+            let rhs = match self.next() {
+                Some(Token::Ident(name)) => units::lookup(&name),
+                _ => None,
+            }
+            .ok_or_else(|| aanyhow!("expected a unit"))?;
+            let rhs = self.unit_power(rhs)?;
+            unit = if op == '*' {
+                unit.mul(&rhs)?
+            } else {
+                unit.div(&rhs)?
+            };
+        }
+    }
+
+    fn unit_power(&mut self, unit: Unit) -> AResult<Unit> {
+        if let Some(Token::Op('^')) = self.peek() {
+            let negative = self.peek_at(1) == Some(&Token::Op('-'));
+            let exp_at = if negative { 2 } else { 1 };
+            if let Some(n) = match self.peek_at(exp_at) {
+                Some(Token::Number(n)) => n.to_i64().filter(|n| *n < 10),
+                _ => None,
+            } {
+                self.pos += exp_at + 1;
+                let n = n as i8;
+                return unit.pow(if negative { -n } else { n });
+            }
+        }
+        Ok(unit)
+    }
+}
@@ -0,0 +1,538 @@
+use std::fmt::Write;
+
+use chin_tools::{aanyhow, AResult};
+use serde::{Deserialize, Serialize};
+
+use super::num::Number;
+
+/// Exponents of the base dimensions: length, mass, time, temperature, information.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Hash, Deserialize, Serialize)]
+pub struct Dim(pub [i8; 5]);
+
+impl Dim {
+    pub const NONE: Dim = Dim([0, 0, 0, 0, 0]);
+    pub const LENGTH: Dim = Dim([1, 0, 0, 0, 0]);
+    pub const MASS: Dim = Dim([0, 1, 0, 0, 0]);
+    pub const TIME: Dim = Dim([0, 0, 1, 0, 0]);
+    pub const TEMPERATURE: Dim = Dim([0, 0, 0, 1, 0]);
+    pub const INFORMATION: Dim = Dim([0, 0, 0, 0, 1]);
+    pub const AREA: Dim = Dim([2, 0, 0, 0, 0]);
+    pub const VOLUME: Dim = Dim([3, 0, 0, 0, 0]);
+    pub const SPEED: Dim = Dim([1, 0, -1, 0, 0]);
+    pub const ACCELERATION: Dim = Dim([1, 0, -2, 0, 0]);
+    pub const FREQUENCY: Dim = Dim([0, 0, -1, 0, 0]);
+    pub const FORCE: Dim = Dim([1, 1, -2, 0, 0]);
+    pub const PRESSURE: Dim = Dim([-1, 1, -2, 0, 0]);
+    pub const ENERGY: Dim = Dim([2, 1, -2, 0, 0]);
+    pub const POWER: Dim = Dim([2, 1, -3, 0, 0]);
+    pub const DATA_RATE: Dim = Dim([0, 0, -1, 0, 1]);
+
+    pub fn is_none(&self) -> bool {
+        *self == Dim::NONE
+    }
+
+    pub fn mul(&self, other: &Dim) -> AResult<Dim> {
+        self.zip(other, i8::checked_add)
+    }
+
+    pub fn div(&self, other: &Dim) -> AResult<Dim> {
+        self.zip(other, i8::checked_sub)
+    }
+
+    pub fn pow(&self, n: i8) -> AResult<Dim> {
+        let mut d = self.0;
+        for e in d.iter_mut() {
+            *e = checked(e.checked_mul(n))?;
+        }
+        Ok(Dim(d))
+    }
+
+    fn zip(&self, other: &Dim, f: fn(i8, i8) -> Option<i8>) -> AResult<Dim> {
+        let mut d = self.0;
+        for (a, b) in d.iter_mut().zip(other.0) {
+            *a = checked(f(*a, b))?;
+        }
+        Ok(Dim(d))
+    }
+
+    /// Returns `None` when some exponent is not a multiple of `n`.
+    pub fn root(&self, n: i8) -> Option<Dim> {
+        if self.0.iter().any(|e| e % n != 0) {
+            None
+        } else {
+            Some(Dim(self.0.map(|e| e / n)))
+        }
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        NAMED_DIMS
+            .iter()
+            .find(|(dim, ..)| dim == self)
+            .map(|(_, name, ..)| *name)
+    }
+}
+
+fn checked(exp: Option<i8>) -> AResult<i8> {
+    exp.ok_or_else(|| aanyhow!("unit exponent out of range"))
+}
+
+/// A resolved unit: `si = value * factor + offset`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Unit {
+    pub symbol: String,
+    pub factor: f64,
+    pub offset: f64,
+    pub dim: Dim,
+    /// The named units `symbol` is made of and their exponents, `m/s²` is `m¹ s⁻²`.
+    #[serde(default)]
+    parts: Vec<(String, i8)>,
+}
+
+impl Unit {
+    pub fn to_si(&self, value: &Number) -> Number {
+        value
+            .mul(&Number::from_f64(self.factor))
+            .add(&Number::from_f64(self.offset))
+    }
+
+    pub fn of_si(&self, si: &Number) -> AResult<Number> {
+        si.sub(&Number::from_f64(self.offset))
+            .div(&Number::from_f64(self.factor))
+    }
+
+    fn named(symbol: &str, factor: f64, offset: f64, dim: Dim) -> Unit {
+        Unit {
+            symbol: symbol.to_string(),
+            factor,
+            offset,
+            dim,
+            parts: vec![(symbol.to_string(), 1)],
+        }
+    }
+
+    /// Compound units never carry an offset, `°C/s` is a plain rate.
+    fn compound(parts: Vec<(String, i8)>, factor: f64, dim: Dim) -> Unit {
+        Unit {
+            symbol: render(&parts),
+            factor,
+            offset: 0.,
+            dim,
+            parts,
+        }
+    }
+
+    /// Units saved before they had parts are a single one.
+    fn parts(&self) -> Vec<(String, i8)> {
+        match self.parts.is_empty() {
+            true => vec![(self.symbol.clone(), 1)],
+            false => self.parts.clone(),
+        }
+    }
+
+    /// `other` raised to `n` and merged into the parts of this unit.
+    fn merge(&self, other: &Unit, n: i8) -> AResult<Vec<(String, i8)>> {
+        let mut parts = self.parts();
+        for (symbol, exp) in other.parts() {
+            let exp = checked(exp.checked_mul(n))?;
+            match parts.iter_mut().find(|(s, _)| *s == symbol) {
+                Some((_, e)) => *e = checked(e.checked_add(exp))?,
+                None => parts.push((symbol, exp)),
+            }
+        }
+        parts.retain(|(_, e)| *e != 0);
+        Ok(parts)
+    }
+
+    pub fn mul(&self, other: &Unit) -> AResult<Unit> {
+        Ok(Unit::compound(
+            self.merge(other, 1)?,
+            self.factor * other.factor,
+            self.dim.mul(&other.dim)?,
+        ))
+    }
+
+    pub fn div(&self, other: &Unit) -> AResult<Unit> {
+        Ok(Unit::compound(
+            self.merge(other, -1)?,
+            self.factor / other.factor,
+            self.dim.div(&other.dim)?,
+        ))
+    }
+
+    /// Raises every part, `(m/s)^2` is `m²/s²`.
+    pub fn pow(&self, n: i8) -> AResult<Unit> {
+        let parts = self
+            .parts()
+            .into_iter()
+            .map(|(symbol, e)| Ok((symbol, checked(e.checked_mul(n))?)))
+            .collect::<AResult<Vec<_>>>()?;
+        Ok(Unit::compound(
+            parts,
+            self.factor.powi(n as i32),
+            self.dim.pow(n)?,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Prefixes {
+    None,
+    /// Every SI prefix, `mm`, `kg`, `ns`...
+    Si,
+    /// Only the multiples and the binary prefixes, `kB`, `MiB`, `Gb`...
+    Data,
+}
+
+struct UnitDef {
+    names: &'static [&'static str],
+    factor: f64,
+    offset: f64,
+    dim: Dim,
+    prefixes: Prefixes,
+}
+
+const fn def(names: &'static [&'static str], factor: f64, dim: Dim, prefixes: Prefixes) -> UnitDef {
+    UnitDef {
+        names,
+        factor,
+        offset: 0.,
+        dim,
+        prefixes,
+    }
+}
+
+const SI_PREFIXES: &[(&str, f64)] = &[
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("µ", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+];
+
+const DATA_PREFIXES: &[(&str, f64)] = &[
+    ("Ki", 1024.),
+    ("Mi", 1048576.),
+    ("Gi", 1073741824.),
+    ("Ti", 1099511627776.),
+    ("Pi", 1125899906842624.),
+    ("k", 1e3),
+    ("K", 1e3),
+    ("M", 1e6),
+    ("G", 1e9),
+    ("T", 1e12),
+    ("P", 1e15),
+];
+
+/// Factors are relative to the SI base units, information is counted in bits.
+const UNITS: &[UnitDef] = &[
+    // length
+    def(
+        &["m", "meter", "meters", "metre", "metres"],
+        1.,
+        Dim::LENGTH,
+        Prefixes::Si,
+    ),
+    def(&["inch", "inches"], 0.0254, Dim::LENGTH, Prefixes::None),
+    def(&["ft", "foot", "feet"], 0.3048, Dim::LENGTH, Prefixes::None),
+    def(
+        &["yd", "yard", "yards"],
+        0.9144,
+        Dim::LENGTH,
+        Prefixes::None,
+    ),
+    def(
+        &["mi", "mile", "miles"],
+        1609.344,
+        Dim::LENGTH,
+        Prefixes::None,
+    ),
+    def(&["nmi"], 1852., Dim::LENGTH, Prefixes::None),
+    def(&["au"], 149597870700., Dim::LENGTH, Prefixes::None),
+    def(
+        &["ly", "lightyear", "lightyears"],
+        9460730472580800.,
+        Dim::LENGTH,
+        Prefixes::None,
+    ),
+    // mass
+    def(&["g", "gram", "grams"], 1e-3, Dim::MASS, Prefixes::Si),
+    def(&["t", "tonne", "tonnes"], 1e3, Dim::MASS, Prefixes::None),
+    def(
+        &["lb", "lbs", "pound", "pounds"],
+        0.45359237,
+        Dim::MASS,
+        Prefixes::None,
+    ),
+    def(
+        &["oz", "ounce", "ounces"],
+        0.028349523125,
+        Dim::MASS,
+        Prefixes::None,
+    ),
+    def(&["st", "stone"], 6.35029318, Dim::MASS, Prefixes::None),
+    // time
+    def(
+        &["s", "sec", "second", "seconds"],
+        1.,
+        Dim::TIME,
+        Prefixes::Si,
+    ),
+    def(
+        &["min", "minute", "minutes"],
+        60.,
+        Dim::TIME,
+        Prefixes::None,
+    ),
+    def(
+        &["h", "hr", "hour", "hours"],
+        3600.,
+        Dim::TIME,
+        Prefixes::None,
+    ),
+    def(&["d", "day", "days"], 86400., Dim::TIME, Prefixes::None),
+    def(&["wk", "week", "weeks"], 604800., Dim::TIME, Prefixes::None),
+    def(&["month", "months"], 2629746., Dim::TIME, Prefixes::None),
+    def(
+        &["yr", "year", "years"],
+        31556952.,
+        Dim::TIME,
+        Prefixes::None,
+    ),
+    // temperature
+    def(&["K", "kelvin"], 1., Dim::TEMPERATURE, Prefixes::None),
+    UnitDef {
+        names: &["C", "°C", "celsius"],
+        factor: 1.,
+        offset: 273.15,
+        dim: Dim::TEMPERATURE,
+        prefixes: Prefixes::None,
+    },
+    UnitDef {
+        names: &["F", "°F", "fahrenheit"],
+        factor: 5. / 9.,
+        offset: 459.67 * 5. / 9.,
+        dim: Dim::TEMPERATURE,
+        prefixes: Prefixes::None,
+    },
+    // information
+    def(&["b", "bit", "bits"], 1., Dim::INFORMATION, Prefixes::Data),
+    def(
+        &["B", "byte", "bytes"],
+        8.,
+        Dim::INFORMATION,
+        Prefixes::Data,
+    ),
+    // speed
+    def(&["mph"], 0.44704, Dim::SPEED, Prefixes::None),
+    def(&["kph", "kmh"], 1. / 3.6, Dim::SPEED, Prefixes::None),
+    def(
+        &["kn", "knot", "knots"],
+        1852. / 3600.,
+        Dim::SPEED,
+        Prefixes::None,
+    ),
+    // area
+    def(
+        &["ha", "hectare", "hectares"],
+        1e4,
+        Dim::AREA,
+        Prefixes::None,
+    ),
+    def(&["acre", "acres"], 4046.8564224, Dim::AREA, Prefixes::None),
+    // volume
+    def(
+        &["L", "l", "liter", "liters", "litre", "litres"],
+        1e-3,
+        Dim::VOLUME,
+        Prefixes::Si,
+    ),
+    def(
+        &["gal", "gallon", "gallons"],
+        3.785411784e-3,
+        Dim::VOLUME,
+        Prefixes::None,
+    ),
+    def(
+        &["qt", "quart", "quarts"],
+        9.46352946e-4,
+        Dim::VOLUME,
+        Prefixes::None,
+    ),
+    def(
+        &["pt", "pint", "pints"],
+        4.73176473e-4,
+        Dim::VOLUME,
+        Prefixes::None,
+    ),
+    def(
+        &["cup", "cups"],
+        2.365882365e-4,
+        Dim::VOLUME,
+        Prefixes::None,
+    ),
+    def(&["floz"], 2.95735295625e-5, Dim::VOLUME, Prefixes::None),
+    // pressure
+    def(&["Pa", "pascal"], 1., Dim::PRESSURE, Prefixes::Si),
+    def(&["bar"], 1e5, Dim::PRESSURE, Prefixes::Si),
+    def(&["atm"], 101325., Dim::PRESSURE, Prefixes::None),
+    def(&["psi"], 6894.757293168361, Dim::PRESSURE, Prefixes::None),
+    def(
+        &["mmHg", "torr"],
+        133.322387415,
+        Dim::PRESSURE,
+        Prefixes::None,
+    ),
+    // energy
+    def(&["J", "joule", "joules"], 1., Dim::ENERGY, Prefixes::Si),
+    def(
+        &["cal", "calorie", "calories"],
+        4.184,
+        Dim::ENERGY,
+        Prefixes::Si,
+    ),
+    def(&["Wh"], 3600., Dim::ENERGY, Prefixes::Si),
+    def(&["eV"], 1.602176634e-19, Dim::ENERGY, Prefixes::Si),
+    def(&["BTU", "btu"], 1055.05585262, Dim::ENERGY, Prefixes::None),
+    // others
+    def(&["N", "newton", "newtons"], 1., Dim::FORCE, Prefixes::Si),
+    def(&["W", "watt", "watts"], 1., Dim::POWER, Prefixes::Si),
+    def(&["Hz", "hertz"], 1., Dim::FREQUENCY, Prefixes::Si),
+];
+
+type Parts = &'static [(&'static str, i8)];
+
+/// Dimensions with a name and the unit their results are shown in, as its parts.
+const NAMED_DIMS: &[(Dim, &str, Parts, f64)] = &[
+    (Dim::LENGTH, "length", &[("m", 1)], 1.),
+    (Dim::MASS, "mass", &[("kg", 1)], 1.),
+    (Dim::TIME, "time", &[("s", 1)], 1.),
+    (Dim::TEMPERATURE, "temperature", &[("K", 1)], 1.),
+    (Dim::INFORMATION, "data size", &[("B", 1)], 8.),
+    (Dim::AREA, "area", &[("m", 2)], 1.),
+    (Dim::VOLUME, "volume", &[("m", 3)], 1.),
+    (Dim::SPEED, "speed", &[("m", 1), ("s", -1)], 1.),
+    (
+        Dim::ACCELERATION,
+        "acceleration",
+        &[("m", 1), ("s", -2)],
+        1.,
+    ),
+    (Dim::FREQUENCY, "frequency", &[("Hz", 1)], 1.),
+    (Dim::FORCE, "force", &[("N", 1)], 1.),
+    (Dim::PRESSURE, "pressure", &[("Pa", 1)], 1.),
+    (Dim::ENERGY, "energy", &[("J", 1)], 1.),
+    (Dim::POWER, "power", &[("W", 1)], 1.),
+    (Dim::DATA_RATE, "data rate", &[("B", 1), ("s", -1)], 8.),
+];
+
+const BASE_SYMBOLS: [&str; 5] = ["m", "kg", "s", "K", "b"];
+
+impl UnitDef {
+    fn to_unit(&self, symbol: &str, scale: f64) -> Unit {
+        Unit::named(symbol, self.factor * scale, self.offset, self.dim)
+    }
+}
+
+/// Resolve a unit name, `km`, `MiB`, `°F` or `hours`.
+pub fn lookup(name: &str) -> Option<Unit> {
+    for def in UNITS {
+        if def.names.contains(&name) {
+            return Some(def.to_unit(name, 1.));
+        }
+    }
+
+    for def in UNITS {
+        let prefixes = match def.prefixes {
+            Prefixes::None => continue,
+            Prefixes::Si => SI_PREFIXES,
+            Prefixes::Data => DATA_PREFIXES,
+        };
+        for (prefix, scale) in prefixes {
+            if let Some(rest) = name.strip_prefix(prefix) {
+                if def.names.contains(&rest) {
+                    return Some(def.to_unit(name, *scale));
+                }
+            }
+        }
+    }
+
+    // be lenient with spelled out names only, `mb` must not become millibits
+    if name.len() > 2 {
+        for def in UNITS {
+            if let Some(n) = def
+                .names
+                .iter()
+                .find(|n| n.len() > 2 && n.eq_ignore_ascii_case(name))
+            {
+                return Some(def.to_unit(n, 1.));
+            }
+        }
+    }
+
+    None
+}
+
+/// The unit a value of `dim` is shown in when the user did not ask for one.
+pub fn canonical(dim: &Dim) -> Unit {
+    let owned = |parts: &[(&str, i8)]| parts.iter().map(|(s, e)| (s.to_string(), *e)).collect();
+    if let Some((_, _, parts, factor)) = NAMED_DIMS.iter().find(|(d, ..)| d == dim) {
+        return Unit::compound(owned(parts), *factor, *dim);
+    }
+    let parts = dim
+        .0
+        .iter()
+        .zip(BASE_SYMBOLS)
+        .filter(|(exp, _)| **exp != 0)
+        .map(|(exp, symbol)| (symbol.to_string(), *exp))
+        .collect();
+    Unit::compound(parts, 1., *dim)
+}
+
+/// `m·kg/s²`, the parts with a positive exponent first.
+fn render(parts: &[(String, i8)]) -> String {
+    let part = |symbol: &str, exp: i8| match exp {
+        1 => symbol.to_string(),
+        e => format!("{}{}", symbol, superscript(e)),
+    };
+    let numer: Vec<String> = parts
+        .iter()
+        .filter(|(_, e)| *e > 0)
+        .map(|(s, e)| part(s, *e))
+        .collect();
+    let mut symbol = if numer.is_empty() {
+        "1".to_string()
+    } else {
+        numer.join("·")
+    };
+    for (s, e) in parts.iter().filter(|(_, e)| *e < 0) {
+        let _ = write!(symbol, "/{}", part(s, -*e));
+    }
+    symbol
+}
+
+fn superscript(n: i8) -> String {
+    n.to_string()
+        .chars()
+        .map(|c| match c {
+            '-' => '⁻',
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4' => '⁴',
+            '5' => '⁵',
+            '6' => '⁶',
+            '7' => '⁷',
+            '8' => '⁸',
+            _ => '⁹',
+        })
+        .collect()
+}
@@ -0,0 +1,179 @@
+//! `.dz` files: gzip compressed in chunks by `dictzip`, so any part of them can be read
+//! without inflating what comes before.
+//!
+//! <https://linux.die.net/man/1/dictzip>
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use chin_tools::{aanyhow, AResult};
+use flate2::read::GzDecoder;
+use flate2::{Decompress, FlushDecompress};
+
+const FHCRC: u8 = 2;
+const FEXTRA: u8 = 4;
+const FNAME: u8 = 8;
+const FCOMMENT: u8 = 16;
+
+pub enum DictZip {
+    /// Chunks are inflated on their own when read.
+    Chunked {
+        path: PathBuf,
+        chunk_len: usize,
+        /// Where every chunk starts in the file, and where the last one ends.
+        offsets: Vec<u64>,
+    },
+    /// Plain gzip without a chunk table, inflated once.
+    Whole(Vec<u8>),
+}
+
+impl DictZip {
+    pub fn open(path: &Path) -> AResult<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 10];
+        reader.read_exact(&mut header)?;
+        if header[..3] != [0x1f, 0x8b, 8] {
+            return Err(aanyhow!("{:?} is not gzip compressed", path));
+        }
+        let flags = header[3];
+        let mut consumed = header.len() as u64;
+
+        let mut chunks = None;
+        if flags & FEXTRA != 0 {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            let mut extra = vec![0u8; u16::from_le_bytes(len) as usize];
+            reader.read_exact(&mut extra)?;
+            consumed += 2 + extra.len() as u64;
+            chunks = chunk_table(&extra);
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let mut skipped = vec![];
+                consumed += reader.read_until(0, &mut skipped)? as u64;
+            }
+        }
+        if flags & FHCRC != 0 {
+            consumed += 2;
+        }
+
+        match chunks {
+            Some((chunk_len, sizes)) => {
+                let mut offsets = Vec::with_capacity(sizes.len() + 1);
+                offsets.push(consumed);
+                for size in sizes {
+                    offsets.push(offsets[offsets.len() - 1] + size as u64);
+                }
+                Ok(DictZip::Chunked {
+                    path: path.to_path_buf(),
+                    chunk_len,
+                    offsets,
+                })
+            }
+            None => Ok(DictZip::Whole(read_all(path)?)),
+        }
+    }
+
+    /// `size` bytes from `offset` of the inflated data.
+    pub fn read(&self, offset: u64, size: usize) -> AResult<Vec<u8>> {
+        let (path, chunk_len, offsets) = match self {
+            DictZip::Whole(data) => {
+                let start = (offset as usize).min(data.len());
+                let end = start.saturating_add(size).min(data.len());
+                return Ok(data[start..end].to_vec());
+            }
+            DictZip::Chunked {
+                path,
+                chunk_len,
+                offsets,
+            } => (path, *chunk_len as u64, offsets),
+        };
+        if size == 0 {
+            return Ok(vec![]);
+        }
+
+        let first = (offset / chunk_len) as usize;
+        let last = ((offset + size as u64 - 1) / chunk_len) as usize;
+        if last + 1 >= offsets.len() {
+            return Err(aanyhow!(
+                "{} bytes at {} are out of {:?}",
+                size,
+                offset,
+                path
+            ));
+        }
+        let mut file = File::open(path)?;
+        let mut data = Vec::with_capacity((last - first + 1) * chunk_len as usize);
+        for chunk in first..=last {
+            let mut compressed = vec![0u8; (offsets[chunk + 1] - offsets[chunk]) as usize];
+            file.seek(SeekFrom::Start(offsets[chunk]))?;
+            file.read_exact(&mut compressed)?;
+            // every chunk ends with a full flush, it inflates without the ones before
+            let mut inflated = Vec::with_capacity(chunk_len as usize);
+            Decompress::new(false).decompress_vec(
+                &compressed,
+                &mut inflated,
+                FlushDecompress::Sync,
+            )?;
+            data.extend_from_slice(&inflated);
+        }
+
+        let start = (offset - first as u64 * chunk_len) as usize;
+        let end = (start + size).min(data.len());
+        Ok(data[start.min(end)..end].to_vec())
+    }
+}
+
+/// The chunk length and the compressed size of every chunk, from the `RA` subfield.
+fn chunk_table(mut extra: &[u8]) -> Option<(usize, Vec<u16>)> {
+    let u16_at = |bytes: &[u8], at: usize| -> Option<u16> {
+        Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+    };
+    while extra.len() >= 4 {
+        let len = u16_at(extra, 2)? as usize;
+        let field = extra.get(4..4 + len)?;
+        if &extra[..2] == b"RA" {
+            let chunk_len = u16_at(field, 2)? as usize;
+            let count = u16_at(field, 4)? as usize;
+            let sizes = (0..count)
+                .map(|i| u16_at(field, 6 + i * 2))
+                .collect::<Option<Vec<u16>>>()?;
+            return (chunk_len > 0).then_some((chunk_len, sizes));
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+/// The whole of a gzip file inflated.
+pub fn read_all(path: &Path) -> AResult<Vec<u8>> {
+    let mut data = vec![];
+    GzDecoder::new(File::open(path)?).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::DictZip;
+
+    #[test]
+    fn test_dictzip() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/plugins/mdict/fixtures");
+        let path = dir.join("stardict/test.dict.dz");
+        let plain = super::read_all(&path).unwrap();
+        let zipped = DictZip::open(&path).unwrap();
+        assert!(matches!(zipped, DictZip::Chunked { ref offsets, .. } if offsets.len() > 3));
+
+        // within one chunk, across several and up to the end
+        for (offset, size) in [(0, 10), (5, 120), (60, 70), (0, plain.len())] {
+            assert_eq!(
+                zipped.read(offset as u64, size).unwrap(),
+                &plain[offset..offset + size]
+            );
+        }
+        assert!(zipped.read(plain.len() as u64 + 64, 10).is_err());
+    }
+}
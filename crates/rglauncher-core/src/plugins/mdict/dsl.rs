@@ -0,0 +1,331 @@
+//! ABBYY Lingvo DSL dictionaries, `.dsl` or gzipped `.dsl.dz`.
+//!
+//! Headwords start at the beginning of a line, the lines of their entry are indented.
+//! Several headwords in a row share the entry below them.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use chin_tools::{AResult, AnyhowContext};
+
+use super::dictzip;
+use super::{escape_attr, escape_html, percent_encode, read_within, DictLookup};
+
+/// Common picture extensions, other `[s]` files are played.
+const PICTURES: [&str; 6] = [".png", ".jpg", ".jpeg", ".gif", ".bmp", ".svg"];
+
+pub struct Dsl {
+    name: String,
+    path: PathBuf,
+    /// Headwords with the entries they have.
+    index: BTreeMap<String, Vec<usize>>,
+    /// Entries as DSL markup.
+    bodies: Vec<String>,
+}
+
+impl Dsl {
+    pub fn open(path: &Path) -> AResult<Self> {
+        let data = match path.to_string_lossy().to_lowercase().ends_with(".dz") {
+            true => dictzip::read_all(path)?,
+            false => std::fs::read(path)?,
+        };
+        let (name, index, bodies) = parse(&decode(&data));
+
+        Ok(Dsl {
+            name: name.unwrap_or_else(|| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            }),
+            path: path.to_path_buf(),
+            index,
+            bodies,
+        })
+    }
+}
+
+/// DSL files are UTF-16 most of the time, UTF-8 otherwise.
+fn decode(data: &[u8]) -> String {
+    let utf16 = |data: &[u8], be: bool| -> String {
+        let units: Vec<u16> = data
+            .chunks_exact(2)
+            .map(|c| match be {
+                true => u16::from_be_bytes([c[0], c[1]]),
+                false => u16::from_le_bytes([c[0], c[1]]),
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match data {
+        [0xff, 0xfe, rest @ ..] => utf16(rest, false),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, true),
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        [_, 0, ..] => utf16(data, false),
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+/// `{{comments}}` may span lines, they go before anything else.
+fn strip_comments(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        stripped.push_str(&rest[..start]);
+        match rest[start..].find("}}") {
+            Some(end) => rest = &rest[start + end + 2..],
+            None => rest = "",
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[allow(clippy::type_complexity)]
+fn parse(text: &str) -> (Option<String>, BTreeMap<String, Vec<usize>>, Vec<String>) {
+    let mut name = None;
+    let mut index: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut bodies = vec![];
+    let mut headwords: Vec<String> = vec![];
+    let mut body = String::new();
+
+    let mut flush = |headwords: &mut Vec<String>, body: &mut String| {
+        if headwords.is_empty() || body.is_empty() {
+            return;
+        }
+        for headword in headwords.drain(..) {
+            index.entry(headword).or_default().push(bodies.len());
+        }
+        bodies.push(std::mem::take(body));
+    };
+
+    for line in strip_comments(text).lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            body.push_str(line.trim());
+            body.push('\n');
+        } else if let Some(header) = line.strip_prefix('#') {
+            if let Some(value) = header.strip_prefix("NAME") {
+                name = Some(value.trim().trim_matches('"').to_string());
+            }
+        } else {
+            flush(&mut headwords, &mut body);
+            headwords.push(headword(line));
+        }
+    }
+    flush(&mut headwords, &mut body);
+
+    (name, index, bodies)
+}
+
+/// The headword without escapes and the `{unsorted parts}` that are shown but not looked up.
+fn headword(line: &str) -> String {
+    let mut word = String::new();
+    let mut chars = line.trim().chars();
+    let mut unsorted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => word.extend(chars.next().filter(|_| !unsorted)),
+            '{' => unsorted = true,
+            '}' => unsorted = false,
+            c if !unsorted => word.push(c),
+            _ => {}
+        }
+    }
+    word.trim().to_string()
+}
+
+/// DSL markup as HTML. Links, sounds and urls carry their target as text, so they are
+/// read whole; all other tags map to one HTML tag or none.
+fn render(body: &str) -> String {
+    let mut html = String::new();
+    let mut rest = body;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            let escaped = rest[1..].chars().next().unwrap_or('\\');
+            html.push_str(&escape_html(&escaped.to_string()));
+            rest = &rest[1 + escaped.len_utf8().min(rest.len() - 1)..];
+        } else if let Some(link) = rest.strip_prefix("<<") {
+            let end = link.find(">>").unwrap_or(link.len());
+            html.push_str(&entry_link(&link[..end]));
+            rest = link.get(end + 2..).unwrap_or_default();
+        } else if c == '[' {
+            let end = rest.find(']').unwrap_or(rest.len());
+            let tag = &rest[1..end];
+            rest = rest.get(end + 1..).unwrap_or_default();
+            let name = tag.split_whitespace().next().unwrap_or_default();
+            if let Some(close) = ["ref", "url", "s"]
+                .into_iter()
+                .find(|t| *t == name)
+                .map(|t| format!("[/{}]", t))
+            {
+                let end = rest.find(&close).unwrap_or(rest.len());
+                let target = rest[..end].trim();
+                html.push_str(&match name {
+                    "ref" => entry_link(target),
+                    "url" => format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape_attr(target),
+                        escape_html(target)
+                    ),
+                    _ if PICTURES.iter().any(|e| target.to_lowercase().ends_with(e)) => {
+                        format!("<img src=\"{}\"/>", percent_encode(target))
+                    }
+                    _ => format!(
+                        "<a href=\"sound://{}\">&#128264;</a>",
+                        percent_encode(target)
+                    ),
+                });
+                rest = rest.get(end + close.len()..).unwrap_or_default();
+            } else {
+                html.push_str(&render_tag(tag));
+            }
+        } else if c == '\n' {
+            html.push_str("<br/>");
+            rest = &rest[1..];
+        } else {
+            let end = rest.find(['\\', '[', '<', '\n']).unwrap_or(rest.len());
+            // a single `<` is text
+            let end = if end == 0 { 1 } else { end };
+            html.push_str(&escape_html(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    html
+}
+
+fn entry_link(word: &str) -> String {
+    let word = word.trim();
+    format!(
+        "<a href=\"entry://{}\">{}</a>",
+        percent_encode(word),
+        escape_html(word)
+    )
+}
+
+fn render_tag(tag: &str) -> String {
+    let (name, value) = tag.split_once(' ').unwrap_or((tag, ""));
+    match name {
+        "b" | "i" | "u" | "sup" | "sub" => format!("<{}>", name),
+        "/b" | "/i" | "/u" | "/sup" | "/sub" => format!("<{}>", name),
+        "c" => format!(
+            "<span style=\"color: {}\">",
+            escape_attr(
+                Some(value.trim())
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or("green")
+            )
+        ),
+        "ex" => "<span class=\"ex\">".to_string(),
+        "com" => "<span class=\"com\">".to_string(),
+        "p" => "<i class=\"abbr\">".to_string(),
+        "/p" => "</i>".to_string(),
+        "/c" | "/ex" | "/com" => "</span>".to_string(),
+        "m" => "<div>".to_string(),
+        name if name.len() == 2 && name.starts_with('m') => {
+            let depth = name[1..].parse::<u32>().unwrap_or(0);
+            format!("<div style=\"margin-left: {}em\">", depth)
+        }
+        "/m" => "</div>".to_string(),
+        // [trn], [lang], [*], [t] and the like only structure the entry
+        _ => String::new(),
+    }
+}
+
+impl DictLookup for Dsl {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lookup_word(&self, word: &str) -> AResult<String> {
+        let entries = self
+            .index
+            .get(word)
+            .with_context(|| format!("{} is not in {}", word, self.name))?;
+        Ok(entries
+            .iter()
+            .filter_map(|i| self.bodies.get(*i))
+            .map(|body| format!("<div class=\"dsl\">{}</div>", render(body)))
+            .collect::<Vec<String>>()
+            .join("<hr/>"))
+    }
+
+    fn keys_with_prefix<'a>(&'a self, prefix: &'a str) -> Box<dyn Iterator<Item = String> + 'a> {
+        Box::new(
+            self.index
+                .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+                .map(|(key, _)| key)
+                .take_while(move |key| key.starts_with(prefix))
+                .cloned(),
+        )
+    }
+
+    /// Pictures and sounds are unpacked into `<name>.dsl.files` next to the dictionary.
+    fn lookup_resource(&self, path: &str) -> AResult<Vec<u8>> {
+        let dsl = self.path.to_string_lossy();
+        let dsl = dsl.strip_suffix(".dz").unwrap_or(&dsl);
+        read_within(Path::new(&format!("{}.files", dsl)), path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{headword, render, Dsl};
+    use crate::plugins::mdict::{entry_word, DictLookup};
+
+    #[test]
+    fn test_dsl() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/plugins/mdict/fixtures");
+        for file in ["dsl/test.dsl", "dsl/test.dsl.dz"] {
+            let dict = Dsl::open(&dir.join(file)).unwrap();
+            assert_eq!(dict.name(), "Test DSL");
+
+            let apple = dict.lookup_word("apple").unwrap();
+            assert!(apple.contains("<b>apple</b>"), "{}", apple);
+            assert!(apple.contains("<a href=\"entry://pear\">pear</a>"));
+            assert!(!apple.contains("hidden"));
+            // headwords in a row share their entry
+            assert_eq!(dict.lookup_word("apples").unwrap(), apple);
+            assert!(dict.lookup_word("pear").unwrap().contains("poire"));
+            assert!(dict.lookup_word("banana").is_err());
+
+            let keys: Vec<String> = dict.keys_with_prefix("app").collect();
+            assert_eq!(keys, vec!["apple", "apples"]);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(headword("go{ing} \\{x\\}"), "go {x}");
+        assert_eq!(
+            render("[m1][b]a[/b] [p]n.[/p] [c red]x[/c] \\[y\\] <z>[/m]"),
+            "<div style=\"margin-left: 1em\"><b>a</b> <i class=\"abbr\">n.</i> \
+             <span style=\"color: red\">x</span> [y] &lt;z&gt;</div>"
+        );
+        assert_eq!(
+            render("[trn]see [ref]pear[/ref], <<plum>>[/trn]\n[s]a.wav[/s][s]b.png[/s]"),
+            "see <a href=\"entry://pear\">pear</a>, <a href=\"entry://plum\">plum</a><br/>\
+             <a href=\"sound://a.wav\">&#128264;</a><img src=\"b.png\"/>"
+        );
+        // quotes stay inside their attribute, `#` and `%` are part of the word
+        assert_eq!(
+            render("[ref]C#[/ref] <<50%\"off>> [c red\"]x[/c] [url]a\"b[/url]"),
+            "<a href=\"entry://C%23\">C#</a> <a href=\"entry://50%25%22off\">50%\"off</a> \
+             <span style=\"color: red&quot;\">x</span> <a href=\"a&quot;b\">a\"b</a>"
+        );
+        assert_eq!(entry_word("entry://C%23").as_deref(), Some("C#"));
+        assert_eq!(
+            entry_word("entry://50%25%22off").as_deref(),
+            Some("50%\"off")
+        );
+    }
+}
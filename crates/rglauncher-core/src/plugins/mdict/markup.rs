@@ -0,0 +1,431 @@
+//! Entries as Pango markup, for previews without a web view.
+//!
+//! Only what Pango has an attribute for is kept: emphasis, sizes, colors and links. Blocks
+//! become line breaks, list items get a bullet or their number; scripts, styles and
+//! pictures are dropped.
+
+use super::escape_attr;
+
+/// Named colors Pango knows as well, others would make the whole markup invalid.
+const COLORS: [&str; 16] = [
+    "black",
+    "gray",
+    "grey",
+    "red",
+    "darkred",
+    "maroon",
+    "green",
+    "darkgreen",
+    "olive",
+    "teal",
+    "blue",
+    "darkblue",
+    "navy",
+    "purple",
+    "brown",
+    "orange",
+];
+
+/// Elements whose content is never shown.
+const HIDDEN: [&str; 5] = ["script", "style", "head", "title", "template"];
+
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    attrs: Vec<(String, String)>,
+    /// What follows the tag.
+    rest: &'a str,
+}
+
+#[derive(Default)]
+struct Writer {
+    markup: String,
+    /// HTML elements with the Pango tag they opened and how to close it.
+    open: Vec<(String, &'static str)>,
+    /// Kinds of the lists the writer is in, with the number of the last item.
+    lists: Vec<(bool, usize)>,
+    started: bool,
+    space: bool,
+    newlines: usize,
+}
+
+impl Writer {
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_ascii_whitespace() {
+                self.space = true;
+                continue;
+            }
+            if self.space && self.started && self.newlines == 0 {
+                self.markup.push(' ');
+            }
+            self.space = false;
+            self.started = true;
+            self.newlines = 0;
+            match c {
+                '&' => self.markup.push_str("&amp;"),
+                '<' => self.markup.push_str("&lt;"),
+                '>' => self.markup.push_str("&gt;"),
+                c => self.markup.push(c),
+            }
+        }
+    }
+
+    /// Ends the line, `lines` of 2 leaves an empty one after it.
+    fn line(&mut self, lines: usize) {
+        self.space = false;
+        if !self.started {
+            return;
+        }
+        while self.newlines < lines {
+            self.markup.push('\n');
+            self.newlines += 1;
+        }
+    }
+
+    fn open(&mut self, name: &str, markup: &str, close: &'static str) {
+        if self.space && self.started && self.newlines == 0 {
+            self.markup.push(' ');
+            self.space = false;
+        }
+        self.markup.push_str(markup);
+        self.open.push((name.to_string(), close));
+    }
+
+    /// Closes `name` and whatever was left open in it.
+    fn close(&mut self, name: &str) {
+        if let Some(at) = self.open.iter().rposition(|(open, _)| open == name) {
+            for (_, close) in self.open.drain(at..).rev() {
+                self.markup.push_str(close);
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        let trimmed = self.markup.trim_end_matches('\n').len();
+        if self.open.is_empty() {
+            self.markup.truncate(trimmed);
+        }
+        for (_, close) in self.open.drain(..).rev() {
+            self.markup.push_str(close);
+        }
+        self.markup
+    }
+}
+
+pub fn to_pango(html: &str) -> String {
+    let mut writer = Writer::default();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            writer.text(&decode_entities(rest));
+            break;
+        };
+        writer.text(&decode_entities(&rest[..start]));
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment
+                .find("-->")
+                .map(|end| &comment[end + 3..])
+                .unwrap_or_default();
+            continue;
+        }
+        let Some(tag) = parse_tag(rest) else {
+            // a `<` which starts no tag is text
+            writer.text("<");
+            rest = &rest[1..];
+            continue;
+        };
+        rest = tag.rest;
+
+        if !tag.closing && HIDDEN.contains(&tag.name.as_str()) {
+            let close = format!("</{}", tag.name);
+            rest = find_ignore_case(rest, &close)
+                .and_then(|end| rest[end..].find('>').map(|gt| &rest[end + gt + 1..]))
+                .unwrap_or_default();
+            continue;
+        }
+        write_tag(&mut writer, &tag);
+    }
+    writer.finish()
+}
+
+fn write_tag(writer: &mut Writer, tag: &Tag) {
+    let name = tag.name.as_str();
+    if tag.closing {
+        match name {
+            "p" | "div" | "blockquote" | "table" | "dl" | "details" => writer.line(2),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "summary" => {
+                writer.close(name);
+                writer.line(1);
+            }
+            "ul" | "ol" => {
+                writer.lists.pop();
+                writer.line(1);
+            }
+            "li" | "tr" | "dt" | "dd" => writer.line(1),
+            _ => {}
+        }
+        writer.close(name);
+        return;
+    }
+
+    let attr = |key: &str| {
+        tag.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    match name {
+        // unlike blocks, every `<br>` counts
+        "br" if writer.started => {
+            writer.markup.push('\n');
+            writer.newlines += 1;
+            writer.space = false;
+        }
+        "p" | "div" | "blockquote" | "table" | "dl" | "details" => writer.line(2),
+        "tr" | "dt" | "dd" => writer.line(1),
+        "hr" => {
+            writer.line(1);
+            writer.text("――――――――");
+            writer.line(1);
+        }
+        "ul" | "ol" => {
+            writer.line(1);
+            writer.lists.push((name == "ol", 0));
+        }
+        "li" => {
+            writer.line(1);
+            let depth = writer.lists.len().max(1);
+            let bullet = match writer.lists.last_mut() {
+                Some((true, number)) => {
+                    *number += 1;
+                    format!("{}.", number)
+                }
+                _ => "•".to_string(),
+            };
+            writer.started = true;
+            writer.markup.push_str(&"    ".repeat(depth - 1));
+            writer.text(&bullet);
+            writer.space = true;
+        }
+        "h1" | "h2" | "h3" => {
+            writer.line(2);
+            writer.open(name, "<span size=\"large\" weight=\"bold\">", "</span>");
+        }
+        "h4" | "h5" | "h6" | "summary" => {
+            writer.line(1);
+            writer.open(name, "<b>", "</b>");
+        }
+        "b" | "strong" => writer.open(name, "<b>", "</b>"),
+        "i" | "em" | "cite" | "var" | "dfn" => writer.open(name, "<i>", "</i>"),
+        "u" | "ins" => writer.open(name, "<u>", "</u>"),
+        "s" | "strike" | "del" => writer.open(name, "<s>", "</s>"),
+        "sup" => writer.open(name, "<sup>", "</sup>"),
+        "sub" => writer.open(name, "<sub>", "</sub>"),
+        "big" => writer.open(name, "<big>", "</big>"),
+        "small" => writer.open(name, "<small>", "</small>"),
+        "code" | "tt" | "kbd" | "samp" => writer.open(name, "<tt>", "</tt>"),
+        // links in links are not followed by Pango
+        "a" if !writer.open.iter().any(|(open, _)| open == "a") => {
+            if let Some(href) = attr("href").filter(|h| !h.is_empty()) {
+                let markup = format!("<a href=\"{}\">", escape_attr(href));
+                writer.open(name, &markup, "</a>");
+            }
+        }
+        "font" | "span" => {
+            let color = attr("color").or_else(|| {
+                attr("style")?.split(';').find_map(|decl| {
+                    let (key, value) = decl.split_once(':')?;
+                    (key.trim().eq_ignore_ascii_case("color")).then_some(value.trim())
+                })
+            });
+            if let Some(color) = color.filter(|c| pango_color(c)) {
+                let markup = format!("<span foreground=\"{}\">", color.to_lowercase());
+                writer.open(name, &markup, "</span>");
+            }
+        }
+        "img" => {
+            if let Some(alt) = attr("alt").filter(|a| !a.trim().is_empty()) {
+                writer.text(&format!("[{}]", alt.trim()));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Hex colors and the few names both HTML and Pango know.
+fn pango_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => COLORS.contains(&color.to_lowercase().as_str()),
+    }
+}
+
+/// The tag at the start of `html`, `None` if it does not start one.
+fn parse_tag(html: &str) -> Option<Tag<'_>> {
+    let mut rest = html.strip_prefix('<')?;
+    let closing = match rest.strip_prefix('/') {
+        Some(after) => {
+            rest = after;
+            true
+        }
+        None => false,
+    };
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    if name_len == 0 || !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        // `<!DOCTYPE>` and `<?xml?>` are skipped like tags without a name
+        return match rest.starts_with(['!', '?']) {
+            true => Some(Tag {
+                name: String::new(),
+                closing,
+                attrs: vec![],
+                rest: &rest[rest.find('>')? + 1..],
+            }),
+            false => None,
+        };
+    }
+    let name = rest[..name_len].to_ascii_lowercase();
+    rest = &rest[name_len..];
+
+    let mut attrs = vec![];
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('>') {
+            return Some(Tag {
+                name,
+                closing,
+                attrs,
+                rest: after,
+            });
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            rest = after;
+            continue;
+        }
+        let key_len = rest
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len());
+        if key_len == 0 {
+            return None;
+        }
+        let key = rest[..key_len].to_ascii_lowercase();
+        rest = rest[key_len..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            rest = after.trim_start();
+            let (raw, after) = match rest.chars().next()? {
+                quote @ ('"' | '\'') => {
+                    let end = rest[1..].find(quote)? + 1;
+                    (&rest[1..end], &rest[end + 1..])
+                }
+                _ => {
+                    let end = rest
+                        .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                        .unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = after;
+        }
+        attrs.push((key, value));
+    }
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    let needle = needle.as_bytes();
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+pub(super) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "middot" => Some('·'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "hellip" => Some('…'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_pango;
+
+    #[test]
+    fn test_to_pango() {
+        assert_eq!(
+            to_pango("<p>a  <B>bold</b>\n<em>it</em></p><p>next&nbsp;one &amp; &#x263A;</p>"),
+            "a <b>bold</b> <i>it</i>\n\nnext\u{a0}one &amp; ☺"
+        );
+        // misnested and unclosed tags still make valid markup
+        assert_eq!(to_pango("<b>a<i>b</b>c<u>d"), "<b>a<i>b</i></b>c<u>d</u>");
+        assert_eq!(
+            to_pango("<head><style>p {}</style></head><script>x < 1</script>1 < 2<br>3"),
+            "1 &lt; 2\n3"
+        );
+        assert_eq!(
+            to_pango("<ol><li>one<li>two</ol><ul><li>x</li></ul>"),
+            "1. one\n2. two\n• x"
+        );
+        assert_eq!(
+            to_pango(
+                "<a href=\"entry://pear\">pear</a> <a>none</a> \
+                 <span style=\"font-size: 2em; color: #C00\">red</span>\
+                 <font color=\"rgb(1,2,3)\">plain</font><img src=\"a.png\" alt=\"pic\">"
+            ),
+            "<a href=\"entry://pear\">pear</a> none \
+             <span foreground=\"#c00\">red</span>plain[pic]"
+        );
+        assert_eq!(
+            to_pango(
+                "<details class=\"dictionary\" open><summary>Test <span>en</span></summary>\
+                 <div class=\"entry\">x</div></details>"
+            ),
+            "<b>Test en</b>\n\nx"
+        );
+    }
+}
@@ -0,0 +1,1187 @@
+use std::collections::{HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+use chin_tools::{aanyhow, AResult, AnyhowContext, EResult};
+use lazy_static::lazy_static;
+use mdict::index_cache;
+use mdict::mdict::Passcode;
+use mdict::mdx_utils::{MDictLookup, MDictMemIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::config::DictConfig;
+use crate::plugins::history::HistoryItem;
+use crate::plugins::{Plugin, PluginResult};
+use crate::userinput::UserInput;
+use crate::util::{fs_utils, score_utils};
+
+mod dictzip;
+mod dsl;
+pub mod markup;
+mod stardict;
+
+use dsl::Dsl;
+use stardict::StarDict;
+
+pub const TYPE_ID: &str = "dict";
+
+/// Dictionary files opened, lowercase.
+const EXTENSIONS: [&str; 4] = [".mdx", ".ifo", ".dsl", ".dsl.dz"];
+
+/// Completions listed per dictionary.
+const PREFIX_LIMIT: usize = 10;
+/// Suggestions for a misspelled word listed per dictionary.
+const SUGGESTION_LIMIT: usize = 5;
+
+/// Resources are served to the preview as `mdict://<dictionary id>/<path>`.
+pub const RESOURCE_SCHEME: &str = "mdict";
+/// Pronunciations are linked as `sound://<path>`.
+const SOUND_SCHEME: &str = "sound://";
+/// Other entries are linked as `entry://<word>`, or `bword://<word>` by StarDict ones.
+const ENTRY_SCHEMES: [&str; 2] = ["entry://", "bword://"];
+/// Entries a preview goes back to at most.
+const HISTORY_LIMIT: usize = 50;
+
+lazy_static! {
+    /// Loaded dictionaries, the preview looks their resources up here.
+    static ref DICTIONARIES: ArcSwap<Vec<Arc<Dictionary>>> = ArcSwap::new(Arc::new(Vec::new()));
+    /// Dictionaries which could not be loaded.
+    static ref FAILURES: ArcSwap<Vec<DictFailure>> = ArcSwap::new(Arc::new(Vec::new()));
+}
+
+/// A dictionary file which could not be loaded, and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DictFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// A dictionary, whatever its format.
+pub trait DictLookup: Send + Sync {
+    /// Shown next to its entries.
+    fn name(&self) -> &str;
+
+    /// The file it was opened from.
+    fn path(&self) -> &Path;
+
+    /// The entry of `word` as HTML.
+    fn lookup_word(&self, word: &str) -> AResult<String>;
+
+    /// Headwords starting with `prefix`, sorted by their bytes.
+    fn keys_with_prefix<'a>(&'a self, prefix: &'a str) -> Box<dyn Iterator<Item = String> + 'a>;
+
+    /// Images, sounds and stylesheets the entries refer to.
+    fn lookup_resource(&self, path: &str) -> AResult<Vec<u8>>;
+}
+
+impl DictLookup for MDictMemIndex {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &Path {
+        MDictMemIndex::path(self)
+    }
+
+    fn lookup_word(&self, word: &str) -> AResult<String> {
+        Ok(MDictLookup::lookup_word(self, word)?)
+    }
+
+    fn keys_with_prefix<'a>(&'a self, prefix: &'a str) -> Box<dyn Iterator<Item = String> + 'a> {
+        Box::new(MDictMemIndex::keys_with_prefix(self, prefix))
+    }
+
+    fn lookup_resource(&self, path: &str) -> AResult<Vec<u8>> {
+        Ok(MDictLookup::lookup_resource(self, path)?.to_vec())
+    }
+}
+
+struct Dictionary {
+    /// Hash of the dictionary path, stable between runs and safe in an uri.
+    id: String,
+    /// The configured name, or the one the dictionary gives itself.
+    name: String,
+    languages: Option<String>,
+    priority: i32,
+    /// The configured stylesheet, its selectors scoped to the section.
+    css: Option<String>,
+    reader: Box<dyn DictLookup>,
+}
+
+#[derive(Clone)]
+pub enum DictMsg {}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct DictResult {
+    pub word: String,
+    /// The entries of every dictionary which has the word, a section each.
+    pub html: String,
+    /// Names of those dictionaries.
+    pub dict: String,
+    dict_ids: Vec<String>,
+    id: String,
+}
+
+impl DictResult {
+    /// Relative urls in the entries resolve against it, resources are looked up in their
+    /// dictionaries in the order of the sections.
+    pub fn base_uri(&self) -> String {
+        format!("{}://{}/", RESOURCE_SCHEME, self.dict_ids.join("."))
+    }
+
+    /// Stands in for the dictionaries still loading.
+    fn loading(count: usize) -> Self {
+        let word = match count {
+            1 => "Loading 1 dictionary".to_string(),
+            n => format!("Loading {} dictionaries", n),
+        };
+        DictResult {
+            html: format!(
+                "<p>{}, their entries show up once they are ready.</p>",
+                word
+            ),
+            word,
+            dict: String::new(),
+            dict_ids: vec![],
+            id: "dict-loading".to_string(),
+        }
+    }
+
+    /// Lists the dictionaries which could not be loaded and why.
+    fn failures(failures: &[DictFailure]) -> Self {
+        let word = match failures.len() {
+            1 => "1 dictionary could not be loaded".to_string(),
+            n => format!("{} dictionaries could not be loaded", n),
+        };
+        let items: String = failures
+            .iter()
+            .map(|f| {
+                format!(
+                    "<li><b>{}</b>: {}</li>",
+                    escape_html(&f.path.to_string_lossy()),
+                    escape_html(&f.reason)
+                )
+            })
+            .collect();
+        DictResult {
+            html: format!("<p>{}:</p><ul>{}</ul>", word, items),
+            word,
+            dict: String::new(),
+            dict_ids: vec![],
+            id: "dict-failures".to_string(),
+        }
+    }
+
+    /// Where the resource of a `sound://` link is served.
+    pub fn sound_uri(&self, link: &str) -> Option<String> {
+        let path = link.strip_prefix(SOUND_SCHEME)?;
+        Some(format!(
+            "{}{}",
+            self.base_uri(),
+            path.trim_start_matches(['/', '\\'])
+        ))
+    }
+}
+
+impl PluginResult for DictResult {
+    fn icon_name(&self) -> &str {
+        "dictionary"
+    }
+
+    fn name(&self) -> &str {
+        self.word.as_str()
+    }
+
+    fn extra(&self) -> Option<&str> {
+        Some(self.dict.as_str()).filter(|dict| !dict.is_empty())
+    }
+
+    fn on_enter(&self) {}
+
+    fn get_type_id(&self) -> &'static str {
+        &TYPE_ID
+    }
+
+    fn get_id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn to_enum(self) -> super::PluginResultEnum {
+        super::PluginResultEnum::MDict(self)
+    }
+}
+
+/// Answers from [`DICTIONARIES`] while the dictionary directory is scanned and loaded in
+/// the background; those indexed before are mapped from the cache first.
+pub struct DictPlugin {
+    config: Arc<DictConfig>,
+    cache_dir: Option<PathBuf>,
+    /// Every dictionary file seen so far, loaded, loading or broken, with the stamp of
+    /// the files it was loaded from.
+    found: Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>,
+    /// Dictionaries not loaded yet.
+    loading: Arc<AtomicUsize>,
+}
+
+impl Dictionary {
+    fn new(reader: Box<dyn DictLookup>, config: &DictConfig) -> Self {
+        let settings = config.dictionary(reader.path());
+        let css = settings.and_then(|s| s.css.as_ref()).and_then(|css| {
+            std::fs::read_to_string(Path::new(&config.dir_path).join(css))
+                .inspect_err(|e| tracing::warn!("unable to read {}: {}", css, e))
+                .ok()
+        });
+        let id = format!(
+            "{:x}",
+            md5::compute(reader.path().to_string_lossy().as_bytes())
+        );
+        let css = css.map(|css| scope_css(&css, &format!("#dict-{}", id)));
+        Self {
+            id,
+            name: settings
+                .and_then(|s| s.name.clone())
+                .unwrap_or_else(|| reader.name().to_string()),
+            languages: settings.and_then(|s| s.languages.clone()),
+            priority: settings.and_then(|s| s.priority).unwrap_or_default(),
+            css,
+            reader,
+        }
+    }
+
+    /// Its entry of `word`, `@@@LINK=` entries followed.
+    fn entry(&self, word: &str) -> Option<String> {
+        let mut key = word.to_string();
+        // a few hops at most, dictionaries with link cycles exist
+        for _ in 0..4 {
+            let html = self.reader.lookup_word(&key).ok()?;
+            match html.strip_prefix("@@@LINK=") {
+                Some(link) => key = link.trim_end_matches(['\r', '\n', '\0']).to_string(),
+                None => return Some(html),
+            }
+        }
+        None
+    }
+
+    /// The entry in a collapsible section titled with the dictionary name.
+    fn section(&self, html: &str) -> String {
+        let languages = self
+            .languages
+            .as_ref()
+            .map(|l| format!(" <span class=\"languages\">{}</span>", escape_html(l)))
+            .unwrap_or_default();
+        let css = self
+            .css
+            .as_ref()
+            .map(|css| format!("<style>{}</style>", css))
+            .unwrap_or_default();
+        format!(
+            "<details id=\"dict-{}\" class=\"dictionary\" open><summary>{}{}</summary>{}\
+             <div class=\"entry\">{}</div></details>",
+            self.id,
+            escape_html(&self.name),
+            languages,
+            css,
+            html
+        )
+    }
+
+    /// MDict indexes may come from the cache, the other formats are read in full.
+    fn open(
+        path: &Path,
+        cache_dir: Option<&Path>,
+        passcode: Option<&Passcode>,
+    ) -> AResult<Box<dyn DictLookup>> {
+        let lower = path.to_string_lossy().to_lowercase();
+        if lower.ends_with(".mdx") {
+            Ok(Box::new(MDictMemIndex::open(path, cache_dir, passcode)?))
+        } else if lower.ends_with(".ifo") {
+            Ok(Box::new(StarDict::open(path)?))
+        } else {
+            Ok(Box::new(Dsl::open(path)?))
+        }
+    }
+}
+
+impl DictPlugin {
+    pub fn new(dict_config: Option<&DictConfig>) -> AResult<Self> {
+        let config = dict_config.cloned().context("missing dict config!")?;
+
+        let cache_dir = fs_utils::cache_home()
+            .map(|home| home.join("dict"))
+            .inspect_err(|e| tracing::warn!("dictionary indexes are not cached: {}", e))
+            .ok();
+
+        let plugin = DictPlugin {
+            config: Arc::new(config),
+            cache_dir,
+            found: Default::default(),
+            loading: Default::default(),
+        };
+        plugin.scan();
+        Ok(plugin)
+    }
+
+    /// Loads the dictionaries added to the directory or changed since the last scan and
+    /// drops the removed ones, along with their cached indexes.
+    fn scan(&self) {
+        let config = self.config.clone();
+        let cache_dir = self.cache_dir.clone();
+        let found = self.found.clone();
+        let loading = self.loading.clone();
+
+        std::thread::spawn(move || {
+            DICTIONARIES.rcu(|loaded| {
+                loaded
+                    .iter()
+                    .filter(|d| d.reader.path().is_file())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            });
+            FAILURES.rcu(|failures| {
+                failures
+                    .iter()
+                    .filter(|f| f.path.is_file())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            });
+
+            let dir = &config.dir_path;
+            let paths = match dictionary_files(dir) {
+                Ok(paths) => paths,
+                Err(e) => {
+                    tracing::warn!("unable to scan dictionaries in {}: {}", dir, e);
+                    return;
+                }
+            };
+            if let Some(cache_dir) = cache_dir.as_deref().filter(|d| d.is_dir()) {
+                let files: Vec<PathBuf> =
+                    paths.iter().filter_map(|p| p.canonicalize().ok()).collect();
+                if let Err(e) = index_cache::prune(cache_dir, &files) {
+                    tracing::warn!("unable to prune {:?}: {}", cache_dir, e);
+                }
+            }
+            let added: Vec<PathBuf> = {
+                let mut found = found.lock().unwrap();
+                found.retain(|p, _| p.is_file());
+                paths
+                    .into_iter()
+                    .filter(|p| {
+                        let stamp = stamp(p);
+                        found.insert(p.clone(), stamp.clone()) != Some(stamp)
+                    })
+                    .filter(|p| {
+                        let enabled = config.enabled(p);
+                        if !enabled {
+                            tracing::info!("{:?} is disabled", p);
+                        }
+                        enabled
+                    })
+                    .collect()
+            };
+            loading.fetch_add(added.len(), Ordering::SeqCst);
+
+            let mut stale = vec![];
+            for p in added {
+                let passcode = Self::passcode(&config, &p);
+                let is_mdx = p.to_string_lossy().to_lowercase().ends_with(".mdx");
+                if !is_mdx {
+                    stale.push((p, passcode));
+                    continue;
+                }
+                let cached = guarded(|| {
+                    Ok(MDictMemIndex::open_cached(
+                        &p,
+                        cache_dir.as_deref(),
+                        passcode.as_ref(),
+                    )?)
+                });
+                match cached {
+                    Ok(Some(mdx)) => Self::loaded(Box::new(mdx), &config),
+                    Ok(None) => {
+                        stale.push((p, passcode));
+                        continue;
+                    }
+                    Err(e) => Self::failed(p, e),
+                }
+                loading.fetch_sub(1, Ordering::SeqCst);
+            }
+            for (p, passcode) in stale {
+                match guarded(|| Dictionary::open(&p, cache_dir.as_deref(), passcode.as_ref())) {
+                    Ok(reader) => Self::loaded(reader, &config),
+                    Err(e) => Self::failed(p, e),
+                }
+                loading.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+    }
+
+    /// The registration code of an encrypted MDict, from the config or a `.key` file next to
+    /// it.
+    fn passcode(config: &DictConfig, path: &Path) -> Option<Passcode> {
+        let passcode = match config.dictionary(path).map(|d| (&d.regcode, &d.email)) {
+            Some((Some(regcode), Some(email))) => Passcode::new(regcode, email),
+            _ => {
+                let key_file = path.with_extension("key");
+                if !key_file.is_file() {
+                    return None;
+                }
+                Passcode::read_key_file(&key_file)
+            }
+        };
+        passcode
+            .inspect_err(|e| tracing::warn!("unable to read the passcode of {:?}: {}", path, e))
+            .ok()
+    }
+
+    /// Kept in [`FAILURES`] until the file is removed.
+    fn failed(path: PathBuf, error: impl std::fmt::Display) {
+        tracing::warn!("unable to load {:?}: {}", path, error);
+        DICTIONARIES.rcu(|loaded| {
+            let mut loaded = Vec::clone(loaded);
+            loaded.retain(|d| !same_file(d.reader.path(), &path));
+            loaded
+        });
+        let failure = DictFailure {
+            path,
+            reason: error.to_string(),
+        };
+        FAILURES.rcu(|failures| {
+            let mut failures = Vec::clone(failures);
+            failures.retain(|f| f.path != failure.path);
+            failures.push(failure.clone());
+            failures
+        });
+    }
+
+    /// Dictionaries are kept by priority, the highest first.
+    fn loaded(reader: Box<dyn DictLookup>, config: &DictConfig) {
+        let dict = Arc::new(Dictionary::new(reader, config));
+        FAILURES.rcu(|failures| {
+            let mut failures = Vec::clone(failures);
+            failures.retain(|f| !same_file(&f.path, dict.reader.path()));
+            failures
+        });
+        DICTIONARIES.rcu(|loaded| {
+            let mut loaded = Vec::clone(loaded);
+            loaded.retain(|d| !same_file(d.reader.path(), dict.reader.path()));
+            loaded.push(dict.clone());
+            loaded
+                .sort_by(|a, b| (b.priority, a.reader.path()).cmp(&(a.priority, b.reader.path())));
+            loaded
+        });
+    }
+
+    /// Headwords starting with `word`, the shortest first.
+    fn complete(&self, word: &str) -> Vec<(DictResult, i32)> {
+        let lower = word.to_lowercase();
+        let mut keys: Vec<String> = DICTIONARIES
+            .load()
+            .iter()
+            .flat_map(|dict| {
+                dict.reader
+                    .keys_with_prefix(word)
+                    .chain(dict.reader.keys_with_prefix(&lower))
+                    .filter(|key| key != word)
+                    .take(PREFIX_LIMIT * 2)
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+        keys.sort_by(|a, b| (a.chars().count(), a).cmp(&(b.chars().count(), b)));
+        keys.dedup();
+        keys.truncate(PREFIX_LIMIT);
+        keys.into_iter()
+            .filter_map(|key| {
+                let extra = key.chars().count() - word.chars().count();
+                let result = lookup(&key)?;
+                Some((result, score_utils::high(0) - extra.min(999) as i32))
+            })
+            .collect()
+    }
+
+    /// Headwords a few edits away from `word`. Only those sharing its first letter are
+    /// compared, a typo there is rare and comparing the whole dictionary is not.
+    fn suggest(&self, word: &str) -> Vec<(DictResult, i32)> {
+        let max = if word.chars().count() <= 4 { 1 } else { 2 };
+        let Some(first) = word.chars().next() else {
+            return vec![];
+        };
+        let lower = word.to_lowercase();
+        let mut firsts = vec![
+            first.to_lowercase().to_string(),
+            first.to_uppercase().to_string(),
+        ];
+        firsts.dedup();
+
+        let mut close: Vec<(usize, String)> = DICTIONARIES
+            .load()
+            .iter()
+            .flat_map(|dict| {
+                firsts
+                    .iter()
+                    .flat_map(|first| dict.reader.keys_with_prefix(first))
+                    .filter_map(|key| {
+                        let distance =
+                            score_utils::edit_distance(&key.to_lowercase(), &lower, max)?;
+                        Some((distance, key))
+                    })
+                    .collect::<Vec<(usize, String)>>()
+            })
+            .collect();
+        close.sort();
+        let mut seen = HashSet::new();
+        close.retain(|(_, key)| seen.insert(key.clone()));
+        close.truncate(SUGGESTION_LIMIT);
+        close
+            .into_iter()
+            .filter_map(|(distance, key)| {
+                let result = lookup(&key)?;
+                Some((result, score_utils::middle(0) - distance as i32))
+            })
+            .collect()
+    }
+}
+
+impl Plugin for DictPlugin {
+    type R = DictResult;
+
+    type T = DictMsg;
+
+    fn handle_input(&self, user_input: &UserInput) -> AResult<Vec<(DictResult, i32)>> {
+        let word = user_input.input.trim();
+        if word.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut res: Vec<(DictResult, i32)> = lookup(word)
+            .into_iter()
+            .map(|e| (e, score_utils::highest(10)))
+            .collect();
+        let found = !res.is_empty();
+        res.extend(self.complete(word));
+        if !found {
+            res.extend(self.suggest(word));
+        }
+        let loading = self.loading.load(Ordering::SeqCst);
+        if loading > 0 {
+            res.push((DictResult::loading(loading), score_utils::low(0)));
+        }
+        let failures = FAILURES.load();
+        if !failures.is_empty() {
+            res.push((DictResult::failures(&failures), score_utils::low(0)));
+        }
+        Ok(res)
+    }
+
+    fn refresh_content(&self) {
+        self.scan();
+    }
+
+    fn get_type_id(&self) -> &'static str {
+        &TYPE_ID
+    }
+
+    /// Looked up words are not remembered.
+    fn add_history(&self, _item: HistoryItem<Self::R>) -> EResult {
+        Ok(())
+    }
+
+    fn get_history<'a>(&self) -> Vec<HistoryItem<Self::R>> {
+        vec![]
+    }
+}
+
+/// One page with the entries of every loaded dictionary which has `word`.
+pub fn lookup(word: &str) -> Option<DictResult> {
+    let dictionaries = DICTIONARIES.load();
+    let found: Vec<(&Arc<Dictionary>, String)> = dictionaries
+        .iter()
+        .filter_map(|dict| Some((dict, dict.entry(word)?)))
+        .collect();
+    if found.is_empty() {
+        return None;
+    }
+    Some(DictResult {
+        word: word.to_string(),
+        html: found
+            .iter()
+            .map(|(dict, html)| dict.section(html))
+            .collect(),
+        dict: found
+            .iter()
+            .map(|(dict, _)| dict.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", "),
+        dict_ids: found.iter().map(|(dict, _)| dict.id.clone()).collect(),
+        id: word.to_string(),
+    })
+}
+
+/// Reads every enabled dictionary in the directory in full, without the cache; the name of
+/// each or why it could not be read.
+pub fn check(config: &DictConfig) -> AResult<Vec<(PathBuf, AResult<String>)>> {
+    Ok(dictionary_files(&config.dir_path)?
+        .into_iter()
+        .filter(|p| config.enabled(p))
+        .map(|p| {
+            let passcode = DictPlugin::passcode(config, &p);
+            let name = guarded(|| Dictionary::open(&p, None, passcode.as_ref()))
+                .map(|reader| reader.name().to_string());
+            (p, name)
+        })
+        .collect())
+}
+
+/// Runs `open`, a panic while reading a broken dictionary is an error like the others.
+fn guarded<T>(open: impl FnOnce() -> AResult<T>) -> AResult<T> {
+    std::panic::catch_unwind(AssertUnwindSafe(open)).unwrap_or_else(|panic| {
+        let reason = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(aanyhow!("panicked: {}", reason))
+    })
+}
+
+/// Files under `dir` with the extension of a dictionary.
+fn dictionary_files(dir: &str) -> AResult<Vec<PathBuf>> {
+    let paths = fs_utils::walk_dir(
+        dir,
+        Some(|p: &str| {
+            let p = p.to_lowercase();
+            EXTENSIONS.iter().any(|ext| p.ends_with(ext))
+        }),
+    )?;
+    Ok(paths.into_iter().map(|dr| dr.path()).collect())
+}
+
+/// Identifies the files of the dictionary at `path` by their size and mtime: those
+/// sharing its stem, MDict `.mdd` resources and StarDict `.idx` files included.
+fn stamp(path: &Path) -> Vec<u8> {
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return vec![];
+    };
+    let prefix = format!("{}.", stem.to_string_lossy());
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    index_cache::stamp(&files).unwrap_or_default()
+}
+
+/// Readers keep the path they were opened with, MDict ones a canonical one.
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b
+        || a.canonicalize()
+            .ok()
+            .is_some_and(|a| b.canonicalize().ok() == Some(a))
+}
+
+/// The word an `entry://` or `bword://` link points to, `None` for links to a part of the
+/// same entry.
+pub fn entry_word(link: &str) -> Option<String> {
+    let word = ENTRY_SCHEMES
+        .iter()
+        .find_map(|scheme| link.strip_prefix(scheme))?;
+    let word = word.split('#').next()?.trim_end_matches('/');
+    let word = percent_decode(word);
+    let word = word.trim();
+    (!word.is_empty()).then(|| word.to_string())
+}
+
+/// Entries shown in a preview, those reached by links can be gone back and forth between.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<DictResult>,
+    at: usize,
+}
+
+impl History {
+    /// A result picked in the launcher starts over.
+    pub fn reset(&mut self, result: DictResult) {
+        self.entries = vec![result];
+        self.at = 0;
+    }
+
+    /// An entry reached by a link, the ones gone back from are dropped.
+    pub fn push(&mut self, result: DictResult) {
+        self.entries.truncate(self.at + 1);
+        self.entries.push(result);
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+        self.at = self.entries.len() - 1;
+    }
+
+    pub fn current(&self) -> Option<&DictResult> {
+        self.entries.get(self.at)
+    }
+
+    pub fn back(&mut self) -> Option<&DictResult> {
+        self.at = self.at.checked_sub(1)?;
+        self.current()
+    }
+
+    pub fn forward(&mut self) -> Option<&DictResult> {
+        if self.at + 1 >= self.entries.len() {
+            return None;
+        }
+        self.at += 1;
+        self.current()
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.at > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.at + 1 < self.entries.len()
+    }
+}
+
+/// What an `mdict://` uri points to: a resource of one of its dictionaries, or a file lying
+/// next to one like stylesheets often do. The mime type is guessed from the extension.
+pub fn lookup_resource(uri: &str) -> AResult<(Vec<u8>, &'static str)> {
+    let (ids, path) = parse_resource_uri(uri).context("not a dictionary resource")?;
+    let loaded = DICTIONARIES.load();
+    let dictionaries: Vec<&Arc<Dictionary>> = ids
+        .split('.')
+        .filter_map(|id| loaded.iter().find(|d| d.id == id))
+        .collect();
+    if dictionaries.is_empty() {
+        return Err(aanyhow!("the dictionary is not loaded"));
+    }
+
+    let mime = mime_type(&path);
+    if let Some(data) = dictionaries
+        .iter()
+        .find_map(|dict| dict.reader.lookup_resource(&path).ok())
+    {
+        return Ok((data, mime));
+    }
+    dictionaries
+        .iter()
+        .find_map(|dict| read_within(dict.reader.path().parent()?, &path).ok())
+        .map(|data| (data, mime))
+        .with_context(|| format!("{} is missing", path))
+}
+
+/// `path` read from `dir`, never from outside of it.
+fn read_within(dir: &Path, path: &str) -> AResult<Vec<u8>> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(aanyhow!("{} is outside of the dictionary", path));
+    }
+    std::fs::read(dir.join(relative)).with_context(|| format!("{} is missing", path))
+}
+
+/// `&`, `<` and `>` of plain text escaped for HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Like [`escape_html`], for a value quoted with `"`.
+fn escape_attr(value: &str) -> String {
+    escape_html(value).replace('"', "&quot;")
+}
+
+/// Rules of `css` limited to the elements under `scope`: every selector is prefixed with
+/// it, `html`, `body` and `:root` stand for the scope itself. Rules nested in `@media` and
+/// `@supports` are scoped too, other at-rules are kept as they are.
+fn scope_css(css: &str, scope: &str) -> String {
+    let mut css = css.to_string();
+    while let Some(start) = css.find("/*") {
+        let end = css[start + 2..]
+            .find("*/")
+            .map_or(css.len(), |e| start + e + 4);
+        css.replace_range(start..end, "");
+    }
+
+    let mut scoped = String::new();
+    let mut rest = css.as_str();
+    while let Some(at) = rest.find(['{', ';']) {
+        let prelude = rest[..at].trim();
+        if rest[at..].starts_with(';') {
+            scoped.push_str(prelude);
+            scoped.push(';');
+            rest = &rest[at + 1..];
+            continue;
+        }
+        let end = matching_brace(rest, at);
+        let block = &rest[at + 1..end];
+        if ["@media", "@supports"]
+            .iter()
+            .any(|r| prelude.starts_with(r))
+        {
+            scoped.push_str(&format!("{} {{{}}}", prelude, scope_css(block, scope)));
+        } else if prelude.starts_with('@') {
+            scoped.push_str(&format!("{} {{{}}}", prelude, block));
+        } else {
+            let selectors: Vec<String> = split_selectors(prelude)
+                .into_iter()
+                .map(|selector| {
+                    let (first, tail) = selector.split_at(
+                        selector
+                            .find(|c: char| c.is_whitespace() || ">+~".contains(c))
+                            .unwrap_or(selector.len()),
+                    );
+                    match first {
+                        "html" | "body" | ":root" => format!("{}{}", scope, tail),
+                        _ => format!("{} {}", scope, selector),
+                    }
+                })
+                .collect();
+            scoped.push_str(&format!("{} {{{}}}", selectors.join(", "), block));
+        }
+        rest = rest.get(end + 1..).unwrap_or_default();
+    }
+    scoped
+}
+
+/// Where the block opened at `open` ends, the end of `css` when it is never closed.
+fn matching_brace(css: &str, open: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in css[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + i;
+                }
+            }
+            _ => {}
+        }
+    }
+    css.len()
+}
+
+/// `a, b:is(c, d)` is two selectors.
+fn split_selectors(selectors: &str) -> Vec<&str> {
+    let (mut parts, mut depth, mut start) = (vec![], 0, 0);
+    for (i, c) in selectors.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(selectors[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(selectors[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+/// The dictionary id and the resource path as the `.mdd` keys it: `/` separated and
+/// without a leading one.
+fn parse_resource_uri(uri: &str) -> Option<(String, String)> {
+    let rest = uri.strip_prefix(RESOURCE_SCHEME)?.strip_prefix("://")?;
+    let (id, path) = rest.split_once('/')?;
+    let path = path.split(['?', '#']).next()?;
+    let path = percent_decode(path).replace('\\', "/");
+    let path = path.trim_start_matches('/');
+    match path.is_empty() {
+        true => None,
+        false => Some((id.to_string(), path.to_string())),
+    }
+}
+
+/// A link target as an uri path, `C#` is `C%23`. [`percent_decode`] reverts it.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn mime_type(path: &str) -> &'static str {
+    let ext = path.rsplit_once('.').map(|(_, e)| e.to_lowercase());
+    match ext.as_deref() {
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("html" | "htm") => "text/html",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg" | "oga") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("spx") => "audio/ogg",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::dsl::Dsl;
+    use super::{
+        check, entry_word, guarded, parse_resource_uri, scope_css, stamp, DictFailure, DictPlugin,
+        DictResult, Dictionary, History,
+    };
+    use crate::config::{DictConfig, DictionaryConfig};
+    use crate::plugins::PluginResult;
+
+    #[test]
+    fn test_resource_uri() {
+        let parsed = |uri| parse_resource_uri(uri);
+        assert_eq!(
+            parsed("mdict://ab12/img/a%20b.png?x=1"),
+            Some(("ab12".to_string(), "img/a b.png".to_string()))
+        );
+        assert_eq!(
+            parsed("mdict://ab12/%5Csound%5Chello.mp3"),
+            Some(("ab12".to_string(), "sound/hello.mp3".to_string()))
+        );
+        assert_eq!(parsed("mdict://ab12/"), None);
+        assert_eq!(parsed("file:///etc/passwd"), None);
+
+        let result = DictResult {
+            word: "hello".to_string(),
+            html: String::new(),
+            dict: "Test".to_string(),
+            dict_ids: vec!["ab12".to_string()],
+            id: "Test@hello".to_string(),
+        };
+        assert_eq!(result.base_uri(), "mdict://ab12/");
+        let both = DictResult {
+            dict_ids: vec!["ab12".to_string(), "cd34".to_string()],
+            ..result.clone()
+        };
+        assert_eq!(both.base_uri(), "mdict://ab12.cd34/");
+        assert_eq!(
+            result.sound_uri("sound://hello.mp3").as_deref(),
+            Some("mdict://ab12/hello.mp3")
+        );
+        assert_eq!(result.sound_uri("entry://hello"), None);
+        assert_eq!(
+            entry_word("entry://ice%20cream/").as_deref(),
+            Some("ice cream")
+        );
+        assert_eq!(entry_word("entry://#sense2"), None);
+        assert_eq!(entry_word("bword://pear").as_deref(), Some("pear"));
+        assert_eq!(entry_word("sound://hello.mp3"), None);
+
+        assert_eq!(DictResult::loading(1).word, "Loading 1 dictionary");
+        assert_eq!(DictResult::loading(3).word, "Loading 3 dictionaries");
+        assert_eq!(DictResult::loading(3).extra(), None);
+
+        let failure = DictFailure {
+            path: PathBuf::from("/dicts/a&b.mdx"),
+            reason: "bad header: truncated".to_string(),
+        };
+        let result = DictResult::failures(&[failure]);
+        assert_eq!(result.word, "1 dictionary could not be loaded");
+        assert!(result
+            .html
+            .contains("<li><b>/dicts/a&amp;b.mdx</b>: bad header: truncated</li>"));
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = std::env::temp_dir().join(format!("rgl-dicts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/plugins/mdict/fixtures");
+        std::fs::copy(fixtures.join("dsl/test.dsl"), dir.join("test.dsl")).unwrap();
+        std::fs::write(dir.join("broken.mdx"), "not a dictionary").unwrap();
+        let config = DictConfig {
+            dir_path: dir.to_string_lossy().to_string(),
+            dictionaries: None,
+        };
+
+        let mut checked = check(&config).unwrap();
+        checked.sort_by(|a, b| a.0.cmp(&b.0));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(checked.len(), 2);
+        let reason = checked[0].1.as_ref().unwrap_err().to_string();
+        assert_eq!(reason, "bad header: truncated");
+        assert_eq!(checked[1].1.as_ref().unwrap(), "Test DSL");
+    }
+
+    #[test]
+    fn test_guarded() {
+        assert_eq!(guarded(|| Ok(1)).unwrap(), 1);
+        let e = guarded::<()>(|| panic!("index out of bounds")).unwrap_err();
+        assert_eq!(e.to_string(), "panicked: index out of bounds");
+    }
+
+    #[test]
+    fn test_stamp() {
+        let dir = std::env::temp_dir().join(format!("rgl-stamp-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["a.ifo", "a.idx", "b.ifo"] {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+        let (a, b) = (stamp(&dir.join("a.ifo")), stamp(&dir.join("b.ifo")));
+        assert!(!a.is_empty());
+
+        // the index changed, the dictionary is loaded again
+        std::fs::write(dir.join("a.idx"), "a longer index").unwrap();
+        let changed = stamp(&dir.join("a.ifo"));
+        let unchanged = stamp(&dir.join("b.ifo"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_ne!(a, changed);
+        assert_eq!(b, unchanged);
+    }
+
+    #[test]
+    fn test_scope_css() {
+        let css = "@import url(a.css);\n/* {comment} */ body { margin: 0 }\n\
+                   p, .a > b:is(i, u) { color: red }\n\
+                   @media (max-width: 600px) { html .x { display: none } }\n\
+                   @font-face { font-family: A; src: url(a.ttf) }";
+        assert_eq!(
+            scope_css(css, "#d"),
+            "@import url(a.css);#d { margin: 0 }#d p, #d .a > b:is(i, u) { color: red }\
+             @media (max-width: 600px) {#d .x { display: none }}\
+             @font-face { font-family: A; src: url(a.ttf) }"
+        );
+        assert_eq!(scope_css("b { color: red", "#d"), "#d b { color: red}");
+    }
+
+    #[test]
+    fn test_dictionary_config() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/plugins/mdict/fixtures");
+        let settings = |file: &str| DictionaryConfig {
+            file: file.to_string(),
+            enabled: None,
+            priority: Some(5),
+            name: Some("Fruits".to_string()),
+            languages: Some("en-fr".to_string()),
+            css: Some("dsl/test.css".to_string()),
+            regcode: None,
+            email: None,
+        };
+        let config = DictConfig {
+            dir_path: dir.to_string_lossy().to_string(),
+            dictionaries: Some(vec![settings("dsl/test.dsl"), settings("test.dsl.dz")]),
+        };
+        assert!(config.dictionary(&dir.join("dsl/test.dsl")).is_some());
+        // a bare file name matches in any directory
+        assert!(config.dictionary(&dir.join("dsl/test.dsl.dz")).is_some());
+        assert!(config.dictionary(&dir.join("stardict/test.ifo")).is_none());
+
+        let path = dir.join("dsl/test.dsl");
+        let dict = Dictionary::new(Box::new(Dsl::open(&path).unwrap()), &config);
+        assert_eq!((dict.name.as_str(), dict.priority), ("Fruits", 5));
+        let section = dict.section(&dict.entry("apple").unwrap());
+        assert!(section.starts_with(&format!(
+            "<details id=\"dict-{0}\" class=\"dictionary\" open><summary>Fruits \
+             <span class=\"languages\">en-fr</span></summary><style>#dict-{0} .dsl b",
+            dict.id
+        )));
+        assert!(section.contains("<b>apple</b>"));
+        assert!(dict.entry("banana").is_none());
+
+        // neither a registration code nor a `.key` file
+        assert!(DictPlugin::passcode(&config, &path).is_none());
+        let mut registered = config.clone();
+        if let Some(d) = registered.dictionaries.as_mut() {
+            d[0].regcode = Some("00112233445566778899aabbccddeeff".to_string());
+            d[0].email = Some("me@example.com".to_string());
+        }
+        assert!(DictPlugin::passcode(&registered, &path).is_some());
+
+        let plain = Dictionary::new(
+            Box::new(Dsl::open(&path).unwrap()),
+            &DictConfig {
+                dictionaries: None,
+                ..config
+            },
+        );
+        assert_eq!((plain.name.as_str(), plain.priority), ("Test DSL", 0));
+        assert!(plain
+            .section("")
+            .contains("<summary>Test DSL</summary><div"));
+    }
+
+    #[test]
+    fn test_history() {
+        let result = |word: &str| DictResult {
+            word: word.to_string(),
+            html: String::new(),
+            dict: String::new(),
+            dict_ids: vec![],
+            id: word.to_string(),
+        };
+        let word = |result: Option<&DictResult>| result.map(|r| r.word.clone());
+
+        let mut history = History::default();
+        assert!(history.current().is_none());
+        assert!(history.back().is_none());
+        history.reset(result("a"));
+        history.push(result("b"));
+        history.push(result("c"));
+        assert_eq!(word(history.back()).as_deref(), Some("b"));
+        assert_eq!(word(history.back()).as_deref(), Some("a"));
+        assert!(history.back().is_none());
+        assert!(!history.can_go_back() && history.can_go_forward());
+        assert_eq!(word(history.forward()).as_deref(), Some("b"));
+
+        // following a link drops what was ahead
+        history.push(result("d"));
+        assert!(!history.can_go_forward());
+        assert!(history.forward().is_none());
+        assert_eq!(word(history.back()).as_deref(), Some("b"));
+
+        history.reset(result("e"));
+        assert!(!history.can_go_back() && !history.can_go_forward());
+        for i in 0..super::HISTORY_LIMIT * 2 {
+            history.push(result(&i.to_string()));
+        }
+        assert_eq!(history.entries.len(), super::HISTORY_LIMIT);
+        assert!(history.forward().is_none());
+    }
+}
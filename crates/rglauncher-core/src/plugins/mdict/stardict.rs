@@ -0,0 +1,351 @@
+//! StarDict dictionaries: an `.ifo`, the `.idx`, the `.dict` or `.dict.dz` and maybe a
+//! `.syn` with synonyms.
+//!
+//! <https://github.com/huzheng001/stardict-3/blob/master/dict/doc/StarDictFileFormat>
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use chin_tools::{aanyhow, AResult, AnyhowContext};
+
+use super::dictzip::{self, DictZip};
+use super::markup::decode_entities;
+use super::{escape_html, percent_encode, read_within, DictLookup};
+
+const IFO_MAGIC: &str = "StarDict's dict ifo file";
+
+enum DictFile {
+    Plain(PathBuf),
+    Zipped(DictZip),
+}
+
+impl DictFile {
+    fn read(&self, offset: u64, size: usize) -> AResult<Vec<u8>> {
+        match self {
+            DictFile::Plain(path) => {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                let mut data = vec![0u8; size];
+                file.read_exact(&mut data)?;
+                Ok(data)
+            }
+            DictFile::Zipped(dz) => dz.read(offset, size),
+        }
+    }
+}
+
+pub struct StarDict {
+    name: String,
+    ifo: PathBuf,
+    /// Types of the fields when the entries don't name them.
+    same_type_sequence: Option<String>,
+    /// Headwords and synonyms with the offset and size of their entries.
+    index: BTreeMap<String, Vec<(u64, u32)>>,
+    dict: DictFile,
+}
+
+impl StarDict {
+    pub fn open(ifo: &Path) -> AResult<Self> {
+        let info = std::fs::read_to_string(ifo)?;
+        let mut lines = info.lines();
+        if lines
+            .next()
+            .map(|l| l.trim_start_matches('\u{feff}').trim())
+            != Some(IFO_MAGIC)
+        {
+            return Err(aanyhow!("{:?} is not a StarDict ifo file", ifo));
+        }
+        let info: BTreeMap<&str, &str> = lines
+            .filter_map(|line| line.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+
+        let base = ifo.with_extension("");
+        let with = |ext: &str| PathBuf::from(format!("{}.{}", base.display(), ext));
+        let idx = match with("idx") {
+            idx if idx.is_file() => std::fs::read(idx)?,
+            _ => dictzip::read_all(&with("idx.gz")).context("the .idx is missing")?,
+        };
+        let dict = match with("dict") {
+            dict if dict.is_file() => DictFile::Plain(dict),
+            _ => DictFile::Zipped(DictZip::open(&with("dict.dz")).context("the .dict is missing")?),
+        };
+
+        let offset_bits = info.get("idxoffsetbits").copied().unwrap_or("32");
+        let entries = parse_idx(&idx, offset_bits == "64")?;
+        let mut index: BTreeMap<String, Vec<(u64, u32)>> = BTreeMap::new();
+        for (word, entry) in entries.iter() {
+            index.entry(word.clone()).or_default().push(*entry);
+        }
+        if let Ok(syn) = std::fs::read(with("syn")) {
+            for (word, i) in parse_syn(&syn) {
+                if let Some((_, entry)) = entries.get(i as usize) {
+                    index.entry(word).or_default().push(*entry);
+                }
+            }
+        }
+
+        Ok(StarDict {
+            name: info
+                .get("bookname")
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| {
+                    base.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                }),
+            ifo: ifo.to_path_buf(),
+            same_type_sequence: info.get("sametypesequence").map(|s| s.to_string()),
+            index,
+            dict,
+        })
+    }
+}
+
+/// Every word with the offset and size of its entry, in file order.
+fn parse_idx(mut idx: &[u8], wide: bool) -> AResult<Vec<(String, (u64, u32))>> {
+    let offset_len = if wide { 8 } else { 4 };
+    let mut entries = vec![];
+    while !idx.is_empty() {
+        let end = idx
+            .iter()
+            .position(|b| *b == 0)
+            .context("unterminated word in the .idx")?;
+        let word = String::from_utf8_lossy(&idx[..end]).into_owned();
+        let numbers = idx
+            .get(end + 1..end + 1 + offset_len + 4)
+            .context("truncated .idx")?;
+        let offset = match wide {
+            true => u64::from_be_bytes(numbers[..8].try_into()?),
+            false => u32::from_be_bytes(numbers[..4].try_into()?) as u64,
+        };
+        let size = u32::from_be_bytes(numbers[offset_len..].try_into()?);
+        entries.push((word, (offset, size)));
+        idx = &idx[end + 1 + offset_len + 4..];
+    }
+    Ok(entries)
+}
+
+/// Synonyms with the position of the word they stand for in the `.idx`.
+fn parse_syn(mut syn: &[u8]) -> Vec<(String, u32)> {
+    let mut synonyms = vec![];
+    while let Some(end) = syn.iter().position(|b| *b == 0) {
+        let Some(i) = syn.get(end + 1..end + 5) else {
+            break;
+        };
+        synonyms.push((
+            String::from_utf8_lossy(&syn[..end]).into_owned(),
+            u32::from_be_bytes([i[0], i[1], i[2], i[3]]),
+        ));
+        syn = &syn[end + 5..];
+    }
+    synonyms
+}
+
+/// The fields of an entry as HTML. Without `same_type_sequence` every field starts with
+/// its type; lowercase types are text ending with a NUL, uppercase ones binary data
+/// after their size. The last field of a sequence has neither.
+fn render(mut data: &[u8], same_type_sequence: Option<&str>) -> String {
+    let mut html = String::new();
+    let types: Vec<char> = same_type_sequence.unwrap_or_default().chars().collect();
+    let mut field = 0;
+    while !data.is_empty() {
+        let (kind, last) = match types.get(field) {
+            Some(kind) => (*kind, field + 1 == types.len()),
+            None if types.is_empty() => {
+                let kind = data[0] as char;
+                data = &data[1..];
+                (kind, false)
+            }
+            None => break,
+        };
+        field += 1;
+
+        let content = match (kind.is_ascii_lowercase(), last) {
+            (_, true) => std::mem::take(&mut data),
+            (true, false) => {
+                let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+                let content = &data[..end];
+                data = data.get(end + 1..).unwrap_or_default();
+                content
+            }
+            (false, false) => {
+                let Some(size) = data.get(..4) else {
+                    break;
+                };
+                let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+                let content = data.get(4..4 + size).unwrap_or_default();
+                data = data.get(4 + size..).unwrap_or_default();
+                content
+            }
+        };
+        html.push_str(&render_field(kind, &String::from_utf8_lossy(content)));
+    }
+    html
+}
+
+fn render_field(kind: char, text: &str) -> String {
+    match kind {
+        // html and pango markup
+        'h' | 'g' => text.to_string(),
+        't' => format!("<p class=\"phonetic\">[{}]</p>", escape_html(text)),
+        'x' => xdxf_to_html(text),
+        'r' => text
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(kind, file)| match kind {
+                "img" => format!("<img src=\"{}\"/>", percent_encode(file)),
+                "snd" => format!("<a href=\"sound://{}\">&#128264;</a>", percent_encode(file)),
+                _ => String::new(),
+            })
+            .collect(),
+        kind if kind.is_ascii_lowercase() => {
+            format!("<p>{}</p>", escape_html(text).replace('\n', "<br/>"))
+        }
+        // pictures, sounds and others are not shown
+        _ => String::new(),
+    }
+}
+
+/// The tags of XDXF articles mapped to HTML, links to other words become `entry://` ones.
+fn xdxf_to_html(text: &str) -> String {
+    let mut html = text.to_string();
+    while let Some(start) = html.find("<kref>") {
+        let Some(end) = html[start..].find("</kref>").map(|e| start + e) else {
+            break;
+        };
+        let word = decode_entities(&html[start + "<kref>".len()..end]);
+        html.replace_range(
+            start..end + "</kref>".len(),
+            &format!(
+                "<a href=\"entry://{}\">{}</a>",
+                percent_encode(&word),
+                escape_html(&word)
+            ),
+        );
+    }
+    [
+        ("<k>", "<b>"),
+        ("</k>", "</b>"),
+        ("<tr>", "<span class=\"phonetic\">["),
+        ("</tr>", "]</span>"),
+        ("<ex>", "<i class=\"ex\">"),
+        ("</ex>", "</i>"),
+        ("<abr>", "<i class=\"abbr\">"),
+        ("</abr>", "</i>"),
+        ("<co>", "<span class=\"co\">"),
+        ("</co>", "</span>"),
+        ("<dtrn>", "<span class=\"dtrn\">"),
+        ("</dtrn>", "</span>"),
+        ("\n", "<br/>"),
+    ]
+    .into_iter()
+    .fold(html, |html, (from, to)| html.replace(from, to))
+}
+
+impl DictLookup for StarDict {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &Path {
+        &self.ifo
+    }
+
+    fn lookup_word(&self, word: &str) -> AResult<String> {
+        let entries = self
+            .index
+            .get(word)
+            .with_context(|| format!("{} is not in {}", word, self.name))?;
+        let rendered = entries
+            .iter()
+            .map(|(offset, size)| {
+                let data = self.dict.read(*offset, *size as usize)?;
+                Ok(render(&data, self.same_type_sequence.as_deref()))
+            })
+            .collect::<AResult<Vec<String>>>()?;
+        Ok(rendered.join("<hr/>"))
+    }
+
+    fn keys_with_prefix<'a>(&'a self, prefix: &'a str) -> Box<dyn Iterator<Item = String> + 'a> {
+        Box::new(
+            self.index
+                .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+                .map(|(key, _)| key)
+                .take_while(move |key| key.starts_with(prefix))
+                .cloned(),
+        )
+    }
+
+    /// Pictures and sounds are kept in `res` next to the `.ifo`.
+    fn lookup_resource(&self, path: &str) -> AResult<Vec<u8>> {
+        let dir = self
+            .ifo
+            .parent()
+            .context("the dictionary has no directory")?;
+        read_within(&dir.join("res"), path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{render, StarDict};
+    use crate::plugins::mdict::DictLookup;
+
+    #[test]
+    fn test_stardict() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/plugins/mdict/fixtures");
+        let dict = StarDict::open(&dir.join("stardict/test.ifo")).unwrap();
+        assert_eq!(dict.name(), "Test StarDict");
+
+        assert_eq!(
+            dict.lookup_word("apple").unwrap(),
+            "<p>a round fruit<br/>with red &amp; green skin</p>"
+        );
+        assert!(dict
+            .lookup_word("banana")
+            .unwrap()
+            .contains("long yellow fruit"));
+        // from the .syn
+        assert_eq!(
+            dict.lookup_word("pomme").unwrap(),
+            dict.lookup_word("apple").unwrap()
+        );
+        assert!(dict.lookup_word("cherry").is_err());
+
+        let keys: Vec<String> = dict.keys_with_prefix("ap").collect();
+        assert_eq!(keys, vec!["apple", "apricot"]);
+        assert!(dict.lookup_resource("../test.ifo").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(b"tfoo\0mbar <1>\0", None),
+            "<p class=\"phonetic\">[foo]</p><p>bar &lt;1&gt;</p>"
+        );
+        assert_eq!(
+            render(b"h<b>hi</b>\0x<k>w</k> <kref>see</kref>\0", None),
+            "<b>hi</b><b>w</b> <a href=\"entry://see\">see</a>"
+        );
+        assert_eq!(
+            render(b"fo\0<i>o</i>", Some("th")),
+            "<p class=\"phonetic\">[fo]</p><i>o</i>"
+        );
+        assert_eq!(
+            render(b"rimg:a.png\nsnd:a.wav\0", None),
+            "<img src=\"a.png\"/><a href=\"sound://a.wav\">&#128264;</a>"
+        );
+        assert_eq!(
+            render(b"x<kref>C#</kref> <kref>say \"hi\" &amp; go</kref>\0", None),
+            "<a href=\"entry://C%23\">C#</a> \
+             <a href=\"entry://say%20%22hi%22%20%26%20go\">say \"hi\" &amp; go</a>"
+        );
+    }
+}
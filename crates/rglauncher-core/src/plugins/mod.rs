@@ -154,6 +154,8 @@ impl Plugin for PluginEnum {
                     });
                 }
             }
+            #[cfg(feature = "mdict")]
+            PluginEnum::Dict(_) => {}
         }
 
         Ok(())
@@ -164,6 +166,8 @@ impl Plugin for PluginEnum {
             PluginEnum::App(p) => p.get_history().into_iter().map(|e| e.into()).collect(),
             PluginEnum::Calc(p) => p.get_history().into_iter().map(|e| e.into()).collect(),
             PluginEnum::Win(p) => p.get_history().into_iter().map(|e| e.into()).collect(),
+            #[cfg(feature = "mdict")]
+            PluginEnum::Dict(p) => p.get_history().into_iter().map(|e| e.into()).collect(),
         }
     }
 }
@@ -189,6 +193,18 @@ pub trait PluginResult: Send + Sync + Clone + DeserializeOwned + Serialize {
 
     fn on_enter(&self);
 
+    /// Secondary action, bound to Shift+Return.
+    fn on_alt_enter(&self) {
+        self.on_enter()
+    }
+
+    /// Further actions by label, the n-th one is bound to Alt+n.
+    fn actions(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn on_action(&self, _index: usize) {}
+
     fn get_type_id(&self) -> &'static str;
 
     fn get_id(&self) -> &str;
@@ -234,6 +250,8 @@ macro_rules! plugin_box {
 plugin_box!(AppResult, App);
 plugin_box!(CalcResult, Calc);
 plugin_box!(WinResult, Win);
+#[cfg(feature = "mdict")]
+plugin_box!(DictResult, MDict);
 
 #[derive(Clone)]
 pub struct PRWrapper {
@@ -262,17 +280,17 @@ where
 }
 
 macro_rules! primpl {
-    ($self:expr, $method:ident) => {
+    ($self:expr, $method:ident $(, $arg:expr)*) => {
         match $self {
-            PluginResultEnum::App(r) => r.$method(),
+            PluginResultEnum::App(r) => r.$method($($arg),*),
             #[cfg(feature = "mdict")]
-            PluginResultEnum::MDict(r) => r.$method(),
+            PluginResultEnum::MDict(r) => r.$method($($arg),*),
             #[cfg(feature = "calc")]
-            PluginResultEnum::Calc(r) => r.$method(),
+            PluginResultEnum::Calc(r) => r.$method($($arg),*),
             #[cfg(feature = "wmwin")]
-            PluginResultEnum::Win(r) => r.$method(),
+            PluginResultEnum::Win(r) => r.$method($($arg),*),
             #[cfg(feature = "clip")]
-            PluginResultEnum::Clip(r) => r.$method(),
+            PluginResultEnum::Clip(r) => r.$method($($arg),*),
         }
     };
 }
@@ -294,6 +312,18 @@ impl PluginResult for PluginResultEnum {
         primpl!(self, on_enter)
     }
 
+    fn on_alt_enter(&self) {
+        primpl!(self, on_alt_enter)
+    }
+
+    fn actions(&self) -> Vec<String> {
+        primpl!(self, actions)
+    }
+
+    fn on_action(&self, index: usize) {
+        primpl!(self, on_action, index)
+    }
+
     fn get_type_id(&self) -> &'static str {
         primpl!(self, get_type_id)
     }
@@ -0,0 +1,362 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use chin_tools::{aanyhow, AResult, AnyhowContext};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use tracing::error;
+
+use super::ipc::Ipc;
+use super::{WMEnum, WinEvent, WinKind, WinResult, Workspace};
+
+lazy_static! {
+    static ref HYPR: Option<Hypr> = Hypr::from_env()
+        .inspect_err(|e| error!("unable to find hyprland: {}", e))
+        .ok();
+}
+
+pub fn client() -> AResult<&'static Hypr> {
+    HYPR.as_ref().context("hyprland socket is not available")
+}
+
+/// `hyprctl` without the process: Hyprland answers one request per connection on
+/// `.socket.sock`, so only the resolved path is kept. Events come from `.socket2.sock`.
+pub struct Hypr {
+    ipc: Ipc,
+    events: PathBuf,
+}
+
+impl Hypr {
+    pub fn new(path: PathBuf, events: PathBuf) -> Self {
+        Self {
+            ipc: Ipc::new(path, false),
+            events,
+        }
+    }
+
+    pub fn from_env() -> AResult<Self> {
+        Ok(Self::new(
+            socket_path(".socket.sock")?,
+            socket_path(".socket2.sock")?,
+        ))
+    }
+
+    pub fn list_windows(&self) -> AResult<Vec<WinResult>> {
+        parse_clients(&self.request("j/clients")?)
+    }
+
+    pub fn list_workspaces(&self) -> AResult<Vec<Workspace>> {
+        parse_workspaces(&self.request("j/workspaces")?)
+    }
+
+    pub fn focus_workspace(&self, id: &str) -> AResult<()> {
+        self.dispatch(&format!("workspace {}", id))
+    }
+
+    pub fn focus_output(&self, name: &str) -> AResult<()> {
+        self.dispatch(&format!("focusmonitor {}", name))
+    }
+
+    pub fn focus_window(&self, address: &str) -> AResult<()> {
+        self.dispatch(&format!("focuswindow address:{}", address))
+    }
+
+    pub fn close_window(&self, address: &str) -> AResult<()> {
+        self.dispatch(&format!("closewindow address:{}", address))
+    }
+
+    pub fn move_to_workspace(&self, address: &str, workspace: &str) -> AResult<()> {
+        // workspaces without a name are named after their id
+        let workspace = match workspace.parse::<i64>() {
+            Ok(_) => workspace.to_string(),
+            Err(_) => format!("name:{}", workspace),
+        };
+        self.dispatch(&format!(
+            "movetoworkspacesilent {},address:{}",
+            workspace, address
+        ))
+    }
+
+    pub fn move_to_current_workspace(&self, address: &str) -> AResult<()> {
+        let workspace: Value = serde_json::from_str(&self.request("j/activeworkspace")?)?;
+        let id = workspace["id"]
+            .as_i64()
+            .context("hyprland has no active workspace")?;
+        self.dispatch(&format!("movetoworkspace {},address:{}", id, address))
+    }
+
+    pub fn toggle_floating(&self, address: &str) -> AResult<()> {
+        self.dispatch(&format!("togglefloating address:{}", address))
+    }
+
+    /// `fullscreen` and `movewindow` only act on the active window.
+    pub fn toggle_fullscreen(&self, address: &str) -> AResult<()> {
+        self.focus_window(address)?;
+        self.dispatch("fullscreen 0")
+    }
+
+    pub fn pull_to_current_output(&self, address: &str) -> AResult<()> {
+        let monitors: Value = serde_json::from_str(&self.request("j/monitors")?)?;
+        let monitor = monitors
+            .as_array()
+            .and_then(|all| all.iter().find(|m| m["focused"] == true))
+            .and_then(|m| m["name"].as_str())
+            .context("hyprland has no focused monitor")?
+            .to_string();
+        self.focus_window(address)?;
+        self.dispatch(&format!("movewindow mon:{}", monitor))
+    }
+
+    fn dispatch(&self, command: &str) -> AResult<()> {
+        match self.request(&format!("dispatch {}", command))? {
+            reply if reply.trim() == "ok" => Ok(()),
+            reply => Err(aanyhow!("unable to {}: {}", command, reply)),
+        }
+    }
+
+    /// The events only carry fragments of a window, so changes fetch the clients again.
+    pub fn watch(&self, on_event: &mut dyn FnMut(WinEvent)) -> AResult<()> {
+        let events = BufReader::new(UnixStream::connect(&self.events)?);
+        for line in events.lines() {
+            match parse_event(&line?) {
+                Some(HyprEvent::Changed) => on_event(WinEvent::Windows(self.list_windows()?)),
+                Some(HyprEvent::Workspaces) => {
+                    on_event(WinEvent::Workspaces(self.list_workspaces()?));
+                    // windows carry the workspace name
+                    on_event(WinEvent::Windows(self.list_windows()?));
+                }
+                Some(HyprEvent::Focused(address)) => on_event(WinEvent::Focused(address.into())),
+                None => {}
+            }
+        }
+        Err(aanyhow!("hyprland closed the event socket"))
+    }
+
+    fn request(&self, request: &str) -> AResult<String> {
+        self.ipc.request(|stream| {
+            stream.get_mut().write_all(request.as_bytes())?;
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply)?;
+            Ok(reply)
+        })
+    }
+}
+
+/// Hyprland moved its sockets from `/tmp/hypr` to `$XDG_RUNTIME_DIR/hypr` in 0.40.
+pub fn socket_path(name: &str) -> AResult<PathBuf> {
+    let signature =
+        std::env::var("HYPRLAND_INSTANCE_SIGNATURE").context("Hyprland is not running")?;
+    let runtime = std::env::var("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("hypr").join(&signature).join(name));
+    match runtime {
+        Ok(path) if path.exists() => Ok(path),
+        _ => Ok(PathBuf::from("/tmp/hypr").join(signature).join(name)),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HyprEvent {
+    /// A window was opened, closed, renamed or moved.
+    Changed,
+    /// A workspace was created, destroyed, renamed or moved to another monitor.
+    Workspaces,
+    Focused(String),
+}
+
+/// One `EVENT>>DATA` line. The v2 duplicates of title and move events are skipped,
+/// the v1 ones are sent by every version.
+pub fn parse_event(line: &str) -> Option<HyprEvent> {
+    let (name, data) = line.split_once(">>")?;
+    match name {
+        "openwindow" | "closewindow" | "windowtitle" | "movewindow" => Some(HyprEvent::Changed),
+        "createworkspace" | "destroyworkspace" | "moveworkspace" | "renameworkspace" => {
+            Some(HyprEvent::Workspaces)
+        }
+        "activewindowv2" if data.starts_with("0x") => Some(HyprEvent::Focused(data.to_string())),
+        "activewindowv2" if !data.trim().is_empty() => {
+            Some(HyprEvent::Focused(format!("0x{}", data)))
+        }
+        _ => None,
+    }
+}
+
+/// `j/clients`, the most recently focused window first.
+pub fn parse_clients(clients: &str) -> AResult<Vec<WinResult>> {
+    let json = serde_json::from_str::<Value>(clients)?;
+
+    let mut array: Vec<&Value> = json
+        .as_array()
+        .context("hyprctl output is not a valid json")?
+        .iter()
+        .collect();
+    array.sort_by_key(|e| e["focusHistoryID"].as_i64().unwrap_or(i64::MAX));
+    let vec: Vec<WinResult> = array
+        .into_iter()
+        .filter_map(|e| {
+            let class = e.get("class")?.as_str()?;
+            let monitor = e.get("monitor")?.as_i64()?;
+            if monitor == -1 {
+                return None;
+            }
+
+            Some(WinResult {
+                class: class.into(),
+                title: e.get("title")?.as_str()?.into(),
+                address: e.get("address")?.as_str()?.into(),
+                pid: e.get("pid")?.as_i64()?,
+                workspace: e.get("workspace")?.get("name")?.as_str()?.into(),
+                wm_type: WMEnum::Hypr,
+                workspaces: vec![],
+                kind: WinKind::Window,
+                members: vec![],
+                summary: None,
+                app: None,
+            })
+        })
+        .collect();
+    Ok(vec)
+}
+
+/// `j/workspaces` by id; special workspaces have a negative one and are left out.
+pub fn parse_workspaces(workspaces: &str) -> AResult<Vec<Workspace>> {
+    let json = serde_json::from_str::<Value>(workspaces)?;
+    let mut array: Vec<&Value> = json
+        .as_array()
+        .context("hyprctl output is not a valid json")?
+        .iter()
+        .filter(|e| e["id"].as_i64().is_some_and(|id| id > 0))
+        .collect();
+    array.sort_by_key(|e| e["id"].as_i64());
+    Ok(array
+        .into_iter()
+        .filter_map(|e| {
+            Some(Workspace {
+                id: e.get("id")?.as_i64()?.to_string().into(),
+                name: e.get("name")?.as_str()?.into(),
+                output: e.get("monitor")?.as_str()?.into(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::super::ipc::fake::FakeServer;
+    use super::{parse_event, parse_workspaces, Hypr, HyprEvent};
+
+    #[test]
+    fn test_hypr() {
+        let server = FakeServer::serve("hypr", false, |request| {
+            match std::str::from_utf8(request).unwrap() {
+                "j/clients" => include_str!("fixtures/hypr_clients.json"),
+                "dispatch focuswindow address:0x5581c1b0e6a0" => "ok",
+                _ => "No such window found",
+            }
+            .as_bytes()
+            .to_vec()
+        });
+        let hypr = Hypr::new(server.path.clone(), server.path.clone());
+
+        let windows = hypr.list_windows().unwrap();
+        assert_eq!(windows.len(), 2);
+        // ordered by focusHistoryID
+        assert_eq!(windows[0].title.as_str(), "~/crate");
+        assert_eq!(windows[1].class.as_str(), "firefox");
+        assert_eq!(windows[1].address.as_str(), "0x5581c1b0e6a0");
+        assert_eq!(windows[1].workspace.as_str(), "2");
+
+        hypr.focus_window("0x5581c1b0e6a0").unwrap();
+        assert!(hypr.focus_window("0x1").is_err());
+        assert_eq!(server.connections(), 3);
+    }
+
+    #[test]
+    fn test_parse_event() {
+        let events: Vec<HyprEvent> = include_str!("fixtures/hypr_events.txt")
+            .lines()
+            .filter_map(parse_event)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                HyprEvent::Focused("0x5581c1b0e6a0".to_string()),
+                HyprEvent::Changed,
+                HyprEvent::Changed,
+                HyprEvent::Changed,
+                HyprEvent::Changed,
+                HyprEvent::Workspaces,
+                HyprEvent::Workspaces,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_workspaces() {
+        let workspaces = parse_workspaces(include_str!("fixtures/hypr_workspaces.json")).unwrap();
+        let summary: Vec<_> = workspaces
+            .iter()
+            .map(|ws| (ws.id.as_str(), ws.name.as_str(), ws.output.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("1", "1", "eDP-1"),
+                ("2", "2", "eDP-1"),
+                ("4", "mail", "DP-1")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_actions() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+        let server = FakeServer::serve("hypr-actions", false, move |request| {
+            let request = std::str::from_utf8(request).unwrap();
+            match request {
+                "j/activeworkspace" => r#"{"id":3,"name":"3"}"#,
+                "j/monitors" => {
+                    r#"[{"name":"DP-1","focused":false},{"name":"eDP-1","focused":true}]"#
+                }
+                dispatch => {
+                    recorded.lock().unwrap().push(dispatch.to_string());
+                    "ok"
+                }
+            }
+            .as_bytes()
+            .to_vec()
+        });
+        let hypr = Hypr::new(server.path.clone(), server.path.clone());
+
+        let address = "0x5581c1b0e6a0";
+        hypr.close_window(address).unwrap();
+        hypr.move_to_workspace(address, "2").unwrap();
+        hypr.move_to_workspace(address, "mail").unwrap();
+        hypr.move_to_current_workspace(address).unwrap();
+        hypr.toggle_floating(address).unwrap();
+        hypr.toggle_fullscreen(address).unwrap();
+        hypr.pull_to_current_output(address).unwrap();
+        hypr.focus_workspace("4").unwrap();
+        hypr.focus_output("DP-1").unwrap();
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "dispatch closewindow address:0x5581c1b0e6a0",
+                "dispatch movetoworkspacesilent 2,address:0x5581c1b0e6a0",
+                "dispatch movetoworkspacesilent name:mail,address:0x5581c1b0e6a0",
+                "dispatch movetoworkspace 3,address:0x5581c1b0e6a0",
+                "dispatch togglefloating address:0x5581c1b0e6a0",
+                "dispatch focuswindow address:0x5581c1b0e6a0",
+                "dispatch fullscreen 0",
+                "dispatch focuswindow address:0x5581c1b0e6a0",
+                "dispatch movewindow mon:eDP-1",
+                "dispatch workspace 4",
+                "dispatch focusmonitor DP-1",
+            ]
+        );
+    }
+}
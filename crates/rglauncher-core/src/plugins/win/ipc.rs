@@ -0,0 +1,165 @@
+use std::io::BufReader;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chin_tools::{aanyhow, AResult};
+use tracing::debug;
+
+/// A compositor socket. When `reuse` is set the stream is kept open between
+/// requests and reopened once if the compositor hung up in the meantime.
+pub struct Ipc {
+    path: PathBuf,
+    reuse: bool,
+    stream: Mutex<Option<BufReader<UnixStream>>>,
+}
+
+impl Ipc {
+    pub fn new(path: PathBuf, reuse: bool) -> Self {
+        Self {
+            path,
+            reuse,
+            stream: Mutex::new(None),
+        }
+    }
+
+    pub fn connect(&self) -> AResult<UnixStream> {
+        Ok(UnixStream::connect(&self.path)?)
+    }
+
+    /// `exchange` writes one request and reads its reply.
+    pub fn request<T>(
+        &self,
+        exchange: impl Fn(&mut BufReader<UnixStream>) -> AResult<T>,
+    ) -> AResult<T> {
+        let mut cached = self
+            .stream
+            .lock()
+            .map_err(|_| aanyhow!("ipc lock is poisoned"))?;
+
+        if let Some(stream) = cached.as_mut() {
+            match exchange(stream) {
+                Ok(reply) => return Ok(reply),
+                Err(e) => {
+                    debug!("reconnecting to {:?}: {}", self.path, e);
+                    *cached = None;
+                }
+            }
+        }
+
+        let mut stream = BufReader::new(self.connect()?);
+        let reply = exchange(&mut stream)?;
+        if self.reuse {
+            *cached = Some(stream);
+        }
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A compositor replaying recorded replies, one per request read.
+    pub struct FakeServer {
+        pub path: PathBuf,
+        connections: Arc<AtomicUsize>,
+        hang_up: Arc<AtomicBool>,
+    }
+
+    impl FakeServer {
+        pub fn serve(
+            name: &str,
+            keep_open: bool,
+            respond: impl Fn(&[u8]) -> Vec<u8> + Send + 'static,
+        ) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "rglauncher-{}-{}.sock",
+                std::process::id(),
+                name
+            ));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            let connections = Arc::new(AtomicUsize::new(0));
+
+            let hang_up = Arc::new(AtomicBool::new(false));
+
+            let counter = connections.clone();
+            let hanging_up = hang_up.clone();
+            std::thread::spawn(move || {
+                for mut stream in listener.incoming().flatten() {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let mut buf = [0; 4096];
+                    while let Ok(n) = stream.read(&mut buf) {
+                        if n == 0 || hanging_up.swap(false, Ordering::SeqCst) {
+                            break;
+                        }
+                        if stream.write_all(&respond(&buf[..n])).is_err() || !keep_open {
+                            break;
+                        }
+                    }
+                }
+            });
+
+            Self {
+                path,
+                connections,
+                hang_up,
+            }
+        }
+
+        pub fn connections(&self) -> usize {
+            self.connections.load(Ordering::SeqCst)
+        }
+
+        /// The connection is closed unanswered on the next request, like a restarted
+        /// compositor does.
+        pub fn hang_up(&self) {
+            self.hang_up.store(true, Ordering::SeqCst);
+        }
+    }
+
+    impl Drop for FakeServer {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Write};
+
+    use chin_tools::aanyhow;
+
+    use super::fake::FakeServer;
+    use super::Ipc;
+
+    #[test]
+    fn test_reconnect() {
+        let server = FakeServer::serve("ipc-reconnect", true, |request| request.to_vec());
+        let ipc = Ipc::new(server.path.clone(), true);
+        let echo = |line: &str| {
+            ipc.request(|stream| {
+                stream.get_mut().write_all(line.as_bytes())?;
+                let mut reply = String::new();
+                match stream.read_line(&mut reply)? {
+                    0 => Err(aanyhow!("connection closed")),
+                    _ => Ok(reply),
+                }
+            })
+        };
+
+        assert_eq!(echo("one\n").unwrap(), "one\n");
+        assert_eq!(echo("two\n").unwrap(), "two\n");
+        assert_eq!(server.connections(), 1);
+
+        server.hang_up();
+        assert_eq!(echo("three\n").unwrap(), "three\n");
+        assert_eq!(server.connections(), 2);
+    }
+}
@@ -0,0 +1,795 @@
+mod hypr;
+mod ipc;
+mod niri;
+mod sway;
+mod toplevel;
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use chin_tools::{aanyhow, AResult, SharedStr};
+use chrono::Utc;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::dispatcher::CONNECTION;
+use crate::plugins::history::{HistoryDb, HistoryItem};
+use crate::plugins::{Plugin, PluginResult};
+use crate::userinput::UserInput;
+
+use crate::util::score_utils;
+
+use super::app::{self, AppResult};
+use super::history::HistoryCache;
+
+pub const TYPE_ID: &str = "wmwindows";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum WMEnum {
+    Niri,
+    Hypr,
+    Sway,
+    /// Any compositor with a foreign toplevel protocol.
+    Toplevel,
+}
+pub trait WMBehavier {
+    fn focus_window(&self, id: &str) -> AResult<()>;
+    fn list_windows(&self) -> AResult<Vec<WinResult>>;
+    /// Blocks while reporting window changes, until the compositor goes away.
+    fn watch(&self, on_event: &mut dyn FnMut(WinEvent)) -> AResult<()>;
+    fn close_window(&self, id: &str) -> AResult<()>;
+    /// Moves the window without following it.
+    fn move_to_workspace(&self, id: &str, workspace: &str) -> AResult<()>;
+    fn move_to_current_workspace(&self, id: &str) -> AResult<()>;
+    fn toggle_floating(&self, id: &str) -> AResult<()>;
+    fn toggle_fullscreen(&self, id: &str) -> AResult<()>;
+    fn pull_to_current_output(&self, id: &str) -> AResult<()>;
+    fn list_workspaces(&self) -> AResult<Vec<Workspace>>;
+    fn focus_workspace(&self, id: &str) -> AResult<()>;
+    fn focus_output(&self, name: &str) -> AResult<()>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workspace {
+    /// What `focus_workspace` takes.
+    pub id: SharedStr,
+    /// As windows label their workspace.
+    pub name: SharedStr,
+    pub output: SharedStr,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub enum WinKind {
+    #[default]
+    Window,
+    Workspace,
+    Output,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WinAction {
+    Close,
+    MoveToCurrentWorkspace,
+    PullToCurrentOutput,
+    ToggleFloating,
+    ToggleFullscreen,
+    MoveToWorkspace(SharedStr),
+}
+
+impl WinAction {
+    pub fn label(&self) -> String {
+        match self {
+            WinAction::Close => "Close".to_string(),
+            WinAction::MoveToCurrentWorkspace => "Move to current workspace".to_string(),
+            WinAction::PullToCurrentOutput => "Pull to current output".to_string(),
+            WinAction::ToggleFloating => "Toggle floating".to_string(),
+            WinAction::ToggleFullscreen => "Toggle fullscreen".to_string(),
+            WinAction::MoveToWorkspace(workspace) => format!("Move to workspace {}", workspace),
+        }
+    }
+}
+
+pub enum WinEvent {
+    /// The complete window list.
+    Windows(Vec<WinResult>),
+    /// The address of the window that got the focus.
+    Focused(SharedStr),
+    /// The complete workspace list.
+    Workspaces(Vec<Workspace>),
+}
+
+impl WMEnum {
+    pub fn new() -> AResult<WMEnum> {
+        if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
+            Ok(WMEnum::Hypr)
+        } else if std::env::var("NIRI_SOCKET").is_ok() {
+            Ok(WMEnum::Niri)
+        } else if std::env::var("SWAYSOCK").is_ok() {
+            Ok(WMEnum::Sway)
+        } else if std::env::var("WAYLAND_DISPLAY").is_ok() {
+            Ok(WMEnum::Toplevel)
+        } else {
+            Err(aanyhow!("no supported window manager found"))
+        }
+    }
+
+    /// What `run` can do besides focusing; workspace moves are added per workspace.
+    fn supported(&self) -> &'static [WinAction] {
+        match self {
+            WMEnum::Niri | WMEnum::Hypr => &[
+                WinAction::Close,
+                WinAction::MoveToCurrentWorkspace,
+                WinAction::PullToCurrentOutput,
+                WinAction::ToggleFloating,
+                WinAction::ToggleFullscreen,
+            ],
+            WMEnum::Sway => &[
+                WinAction::Close,
+                WinAction::ToggleFloating,
+                WinAction::ToggleFullscreen,
+            ],
+            WMEnum::Toplevel => &[WinAction::Close],
+        }
+    }
+
+    pub fn run(&self, id: &str, action: &WinAction) -> AResult<()> {
+        match action {
+            WinAction::Close => self.close_window(id),
+            WinAction::MoveToCurrentWorkspace => self.move_to_current_workspace(id),
+            WinAction::PullToCurrentOutput => self.pull_to_current_output(id),
+            WinAction::ToggleFloating => self.toggle_floating(id),
+            WinAction::ToggleFullscreen => self.toggle_fullscreen(id),
+            WinAction::MoveToWorkspace(workspace) => self.move_to_workspace(id, workspace),
+        }
+    }
+
+    fn unsupported(&self, what: &str) -> AResult<()> {
+        Err(aanyhow!("{:?} can't {}", self, what))
+    }
+}
+
+impl WMBehavier for WMEnum {
+    fn focus_window(&self, id: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.focus_window(id),
+            WMEnum::Hypr => hypr::client()?.focus_window(id),
+            WMEnum::Sway => sway::focus_window(id),
+            WMEnum::Toplevel => toplevel::focus_window(id),
+        }
+    }
+
+    fn list_windows(&self) -> AResult<Vec<WinResult>> {
+        match self {
+            WMEnum::Niri => niri::client()?.list_windows(),
+            WMEnum::Hypr => hypr::client()?.list_windows(),
+            WMEnum::Sway => sway::list_windows(),
+            WMEnum::Toplevel => toplevel::list_windows(),
+        }
+    }
+
+    fn watch(&self, on_event: &mut dyn FnMut(WinEvent)) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.watch(on_event),
+            WMEnum::Hypr => hypr::client()?.watch(on_event),
+            WMEnum::Sway | WMEnum::Toplevel => Err(aanyhow!(
+                "no window events from {:?}, refreshing on open",
+                self
+            )),
+        }
+    }
+
+    fn close_window(&self, id: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.close_window(id),
+            WMEnum::Hypr => hypr::client()?.close_window(id),
+            WMEnum::Sway => sway::run_command(id, "kill"),
+            WMEnum::Toplevel => toplevel::close_window(id),
+        }
+    }
+
+    fn move_to_workspace(&self, id: &str, workspace: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.move_to_workspace(id, workspace),
+            WMEnum::Hypr => hypr::client()?.move_to_workspace(id, workspace),
+            WMEnum::Sway => sway::run_command(
+                id,
+                &format!(
+                    "move container to workspace {}",
+                    serde_json::to_string(workspace)?
+                ),
+            ),
+            WMEnum::Toplevel => self.unsupported("move windows"),
+        }
+    }
+
+    fn move_to_current_workspace(&self, id: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.move_to_current_workspace(id),
+            WMEnum::Hypr => hypr::client()?.move_to_current_workspace(id),
+            WMEnum::Sway | WMEnum::Toplevel => self.unsupported("move windows here"),
+        }
+    }
+
+    fn toggle_floating(&self, id: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.toggle_floating(id),
+            WMEnum::Hypr => hypr::client()?.toggle_floating(id),
+            WMEnum::Sway => sway::run_command(id, "floating toggle"),
+            WMEnum::Toplevel => self.unsupported("toggle floating"),
+        }
+    }
+
+    fn toggle_fullscreen(&self, id: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.toggle_fullscreen(id),
+            WMEnum::Hypr => hypr::client()?.toggle_fullscreen(id),
+            WMEnum::Sway => sway::run_command(id, "fullscreen toggle"),
+            WMEnum::Toplevel => self.unsupported("toggle fullscreen"),
+        }
+    }
+
+    fn pull_to_current_output(&self, id: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.pull_to_current_output(id),
+            WMEnum::Hypr => hypr::client()?.pull_to_current_output(id),
+            WMEnum::Sway | WMEnum::Toplevel => self.unsupported("move windows between outputs"),
+        }
+    }
+
+    fn list_workspaces(&self) -> AResult<Vec<Workspace>> {
+        match self {
+            WMEnum::Niri => niri::client()?.list_workspaces(),
+            WMEnum::Hypr => hypr::client()?.list_workspaces(),
+            WMEnum::Sway => sway::list_workspaces(),
+            WMEnum::Toplevel => Ok(vec![]),
+        }
+    }
+
+    fn focus_workspace(&self, id: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.focus_workspace(id),
+            WMEnum::Hypr => hypr::client()?.focus_workspace(id),
+            WMEnum::Sway => sway::command(&format!("workspace {}", serde_json::to_string(id)?)),
+            WMEnum::Toplevel => self.unsupported("focus workspaces"),
+        }
+    }
+
+    fn focus_output(&self, name: &str) -> AResult<()> {
+        match self {
+            WMEnum::Niri => niri::client()?.focus_output(name),
+            WMEnum::Hypr => hypr::client()?.focus_output(name),
+            WMEnum::Sway => {
+                sway::command(&format!("focus output {}", serde_json::to_string(name)?))
+            }
+            WMEnum::Toplevel => self.unsupported("focus outputs"),
+        }
+    }
+}
+
+/// Known windows keep their focus order, new ones go first.
+fn merge(previous: &[WinResult], fresh: Vec<WinResult>) -> Vec<WinResult> {
+    let mut fresh = fresh;
+    fresh.sort_by_key(|w| {
+        previous
+            .iter()
+            .position(|p| p.address == w.address)
+            .map_or(0, |rank| rank + 1)
+    });
+    fresh
+}
+
+fn focus(windows: &[WinResult], address: &str) -> Option<Vec<WinResult>> {
+    let index = windows.iter().position(|w| w.address.as_str() == address)?;
+    let mut windows = windows.to_vec();
+    let focused = windows.remove(index);
+    windows.insert(0, focused);
+    Some(windows)
+}
+
+/// What the compositor has, kept current by `refresh_content` or the event stream.
+#[derive(Default)]
+struct Live {
+    /// The most recently focused first.
+    windows: ArcSwap<Vec<WinResult>>,
+    workspaces: ArcSwap<Vec<Workspace>>,
+}
+
+impl Live {
+    fn apply(&self, event: WinEvent) {
+        match event {
+            WinEvent::Windows(fresh) => {
+                // the launcher's own window is no switching target
+                let fresh: Vec<WinResult> = fresh
+                    .into_iter()
+                    .filter(|w| w.pid != std::process::id() as i64)
+                    .map(WinResult::with_app)
+                    .collect();
+                self.windows.rcu(|current| merge(current, fresh.clone()));
+            }
+            WinEvent::Focused(address) => {
+                self.windows
+                    .rcu(|current| focus(current, &address).unwrap_or_else(|| current.to_vec()));
+            }
+            WinEvent::Workspaces(workspaces) => self.workspaces.store(workspaces.into()),
+        }
+    }
+}
+
+/// The windows, each knowing where it can be moved, followed by workspaces and outputs.
+fn targets(windows: &[WinResult], workspaces: &[Workspace], wm_type: &WMEnum) -> Vec<WinResult> {
+    let mut names: Vec<SharedStr> = match workspaces.is_empty() {
+        true => windows.iter().map(|w| w.workspace.clone()).collect(),
+        false => workspaces.iter().map(|ws| ws.name.clone()).collect(),
+    };
+    names.retain(|ws| !ws.is_empty());
+    names.sort();
+    names.dedup();
+
+    let members = |on: &dyn Fn(&WinResult) -> bool| -> Vec<(SharedStr, SharedStr)> {
+        windows
+            .iter()
+            .filter(|w| on(w))
+            .map(|w| (w.class.clone(), w.title.clone()))
+            .collect()
+    };
+    let place =
+        |kind, title: &SharedStr, address: &SharedStr, members, summary: String| WinResult {
+            class: "".into(),
+            title: title.clone(),
+            address: address.clone(),
+            pid: 0,
+            workspace: "".into(),
+            wm_type: wm_type.clone(),
+            workspaces: vec![],
+            kind,
+            members,
+            summary: Some(summary.into()),
+            app: None,
+        };
+
+    let mut targets: Vec<WinResult> = windows
+        .iter()
+        .map(|w| WinResult {
+            workspaces: names.clone(),
+            ..w.clone()
+        })
+        .collect();
+
+    for ws in workspaces {
+        let members = members(&|w| w.workspace == ws.name);
+        let summary = match ws.output.is_empty() {
+            true => count(members.len(), "window"),
+            false => format!("{} · {}", ws.output, count(members.len(), "window")),
+        };
+        let mut result = place(WinKind::Workspace, &ws.name, &ws.id, members, summary);
+        result.workspace = ws.output.clone();
+        targets.push(result);
+    }
+
+    let mut outputs: Vec<&SharedStr> = workspaces.iter().map(|ws| &ws.output).collect();
+    outputs.retain(|o| !o.is_empty());
+    outputs.sort();
+    outputs.dedup();
+    for output in outputs {
+        let on: Vec<&SharedStr> = workspaces
+            .iter()
+            .filter(|ws| ws.output == *output)
+            .map(|ws| &ws.name)
+            .collect();
+        let members = members(&|w| on.contains(&&w.workspace));
+        let summary = format!(
+            "{} · {}",
+            count(on.len(), "workspace"),
+            count(members.len(), "window")
+        );
+        targets.push(place(WinKind::Output, output, output, members, summary));
+    }
+
+    targets
+}
+
+fn count(n: usize, what: &str) -> String {
+    match n {
+        1 => format!("1 {}", what),
+        n => format!("{} {}s", n, what),
+    }
+}
+
+#[derive(Clone)]
+pub enum WindowMsg {}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WinResult {
+    pub class: SharedStr,
+    pub title: SharedStr,
+    pub address: SharedStr,
+    pub pid: i64,
+    pub workspace: SharedStr,
+    pub wm_type: WMEnum,
+    /// All known workspaces, targets of the move actions.
+    #[serde(skip)]
+    pub workspaces: Vec<SharedStr>,
+    /// Workspaces and outputs are results too, their `workspace` is the output they are on.
+    #[serde(default)]
+    pub kind: WinKind,
+    /// The windows on a workspace or output, as (class, title).
+    #[serde(skip)]
+    pub members: Vec<(SharedStr, SharedStr)>,
+    /// Shown instead of the workspace.
+    #[serde(skip)]
+    pub summary: Option<SharedStr>,
+    /// The desktop entry of a window, for its icon and localized name.
+    #[serde(skip)]
+    pub app: Option<AppResult>,
+}
+
+impl WinResult {
+    /// Looks up the desktop entry of the window and names it in the summary.
+    fn with_app(mut self) -> Self {
+        self.app = app::find_by_app_id(&self.class);
+        if let Some(app) = self.app.as_ref().filter(|app| !app.app_name.is_empty()) {
+            self.summary = Some(match self.workspace.is_empty() {
+                true => app.app_name.clone(),
+                false => format!("{} · {}", app.app_name, self.workspace).into(),
+            });
+        }
+        self
+    }
+}
+
+impl WinResult {
+    /// At most nine, one per Alt+digit.
+    fn win_actions(&self) -> Vec<WinAction> {
+        if self.kind != WinKind::Window {
+            return vec![];
+        }
+        let mut actions = self.wm_type.supported().to_vec();
+        if !matches!(self.wm_type, WMEnum::Toplevel) {
+            actions.extend(
+                self.workspaces
+                    .iter()
+                    .filter(|ws| **ws != self.workspace)
+                    .map(|ws| WinAction::MoveToWorkspace(ws.clone())),
+            );
+        }
+        actions.truncate(9);
+        actions
+    }
+}
+
+impl PluginResult for WinResult {
+    fn icon_name(&self) -> &str {
+        match self.kind {
+            WinKind::Window => match self.app.as_ref() {
+                Some(app) if !app.icon_name.is_empty() => app.icon_name.as_str(),
+                _ => self.class.as_str(),
+            },
+            WinKind::Workspace => "workspace-switcher",
+            WinKind::Output => "video-display",
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.title.as_str()
+    }
+
+    fn extra(&self) -> Option<&str> {
+        match self.summary.as_ref() {
+            Some(summary) => Some(summary.as_str()),
+            None => Some(self.workspace.as_str()),
+        }
+    }
+
+    fn on_enter(&self) {
+        let focused = match self.kind {
+            WinKind::Window => self.wm_type.focus_window(&self.address),
+            WinKind::Workspace => self.wm_type.focus_workspace(&self.address),
+            WinKind::Output => self.wm_type.focus_output(&self.address),
+        };
+        if let Err(e) = focused {
+            error!("unable to focus on {}", e);
+        }
+    }
+
+    fn actions(&self) -> Vec<String> {
+        self.win_actions().iter().map(WinAction::label).collect()
+    }
+
+    fn on_action(&self, index: usize) {
+        if let Some(action) = self.win_actions().get(index) {
+            if let Err(e) = self.wm_type.run(&self.address, action) {
+                error!("unable to {}: {}", action.label(), e);
+            }
+        }
+    }
+
+    fn get_type_id(&self) -> &'static str {
+        &TYPE_ID
+    }
+
+    fn get_id(&self) -> &str {
+        self.address.as_str()
+    }
+
+    fn to_enum(self) -> super::PluginResultEnum {
+        super::PluginResultEnum::Win(self)
+    }
+}
+
+pub struct WinPlugin {
+    live: Arc<Live>,
+    history: HistoryCache<WinResult>,
+    wm_type: Option<WMEnum>,
+}
+
+impl WinPlugin {
+    /// A missing or unreachable window manager leaves the plugin empty instead of failing.
+    pub fn new() -> AResult<Self> {
+        info!("Creating Windows Plugin");
+        let wm_type = WMEnum::new()
+            .inspect_err(|e| error!("window switcher disabled: {}", e))
+            .ok();
+        let live: Arc<Live> = Default::default();
+        if let Some(wm) = wm_type.as_ref() {
+            match wm.list_windows() {
+                Ok(listed) => live.apply(WinEvent::Windows(listed)),
+                Err(e) => error!("unable to list windows: {}", e),
+            }
+            match wm.list_workspaces() {
+                Ok(listed) => live.apply(WinEvent::Workspaces(listed)),
+                Err(e) => error!("unable to list workspaces: {}", e),
+            }
+            watch(wm.clone(), live.clone());
+        }
+        let wins = live.windows.load_full();
+
+        let histories: Vec<HistoryItem<WinResult>> =
+            CONNECTION.with_borrow(|e| HistoryDb::new(e.as_ref()).fetch_histories(TYPE_ID))?;
+        let history = HistoryCache::new(histories);
+
+        let _ = CONNECTION.with_borrow(|e| {
+            let ho = HistoryDb::new(e.as_ref());
+            history.remove_unvalid(
+                |_, v| {
+                    wins.iter()
+                        .find(|w| w.get_id() == v.body.address.as_str())
+                        .is_some()
+                },
+                ho,
+            )
+        });
+
+        Ok(WinPlugin {
+            live,
+            wm_type,
+            history,
+        })
+    }
+}
+
+impl Plugin for WinPlugin {
+    type R = WinResult;
+
+    type T = WindowMsg;
+
+    fn refresh_content(&self) {
+        let Some(wm_type) = self.wm_type.as_ref() else {
+            return;
+        };
+        if let Ok(windows) = wm_type.list_windows() {
+            CONNECTION.with_borrow(|conn| {
+                let ho = HistoryDb::new(conn.as_ref());
+
+                let _ = self.history.remove_unvalid(
+                    |_, v| windows.iter().find(|w| w.get_id() == v.body.get_id()).is_some(),
+                    ho,
+                );
+            });
+            self.live.apply(WinEvent::Windows(windows));
+        }
+        if let Ok(workspaces) = wm_type.list_workspaces() {
+            self.live.apply(WinEvent::Workspaces(workspaces));
+        }
+    }
+
+    fn handle_input(&self, user_input: &UserInput) -> AResult<Vec<(WinResult, i32)>> {
+        let Some(wm_type) = self.wm_type.as_ref() else {
+            return Ok(vec![]);
+        };
+        let matcher = SkimMatcherV2::default();
+        let mut result = vec![];
+
+        let targets = targets(
+            &self.live.windows.load(),
+            &self.live.workspaces.load(),
+            wm_type,
+        );
+        for window in targets.into_iter() {
+            let mut score: i32 = 0;
+
+            let mut match_str = window.class.to_string();
+            match_str += window.title.as_str();
+            match_str += window.workspace.as_str();
+
+            if user_input.input.is_empty() {
+                score = 100;
+            } else if let Some(_s) =
+                matcher.fuzzy_match(match_str.as_str(), user_input.input.as_str())
+            {
+                score = _s as i32;
+            }
+
+            if score > 0 {
+                result.push((window, score_utils::high(score as i64)));
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn get_type_id(&self) -> &'static str {
+        &TYPE_ID
+    }
+
+    /// Only windows, workspaces and outputs are found by name anyway.
+    fn add_history(&self, item: HistoryItem<Self::R>) -> chin_tools::EResult {
+        if item.body.kind != WinKind::Window {
+            return Ok(());
+        }
+        CONNECTION.with_borrow(|conn| {
+            self.history
+                .add_history(item, HistoryDb::new(conn.as_ref()))
+        })
+    }
+
+    /// Without input the windows are listed like Alt+Tab, the most recently focused first.
+    fn get_history<'a>(&self) -> Vec<HistoryItem<Self::R>> {
+        let Some(wm_type) = self.wm_type.as_ref() else {
+            return vec![];
+        };
+        let windows: Vec<WinResult> = targets(
+            &self.live.windows.load(),
+            &self.live.workspaces.load(),
+            wm_type,
+        )
+        .into_iter()
+        .filter(|w| w.kind == WinKind::Window)
+        .collect();
+        let histories = self.history.histories.load();
+        windows
+            .iter()
+            .enumerate()
+            .map(|(rank, window)| {
+                let id: SharedStr = (TYPE_ID.to_owned() + window.get_id()).into();
+                HistoryItem {
+                    update_time: histories
+                        .get(&id)
+                        .map(|h| h.update_time)
+                        .unwrap_or_else(|| Utc::now().naive_utc()),
+                    id,
+                    plugin_type: TYPE_ID.to_owned(),
+                    body: window.clone(),
+                    weight: (score_utils::high(0) as usize + windows.len() - rank) as f64,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Keeps the list current in the background when the compositor streams events.
+fn watch(wm: WMEnum, live: Arc<Live>) {
+    let spawned = std::thread::Builder::new()
+        .name("rgl-wm-events".into())
+        .spawn(move || {
+            if let Err(e) = wm.watch(&mut |event| live.apply(event)) {
+                info!("stop watching windows: {}", e);
+            }
+        });
+    if let Err(e) = spawned {
+        error!("unable to watch windows: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{focus, merge, targets, WMEnum, WinKind, WinResult, Workspace};
+    use crate::plugins::PluginResult;
+
+    fn windows(addresses: &[&str]) -> Vec<WinResult> {
+        addresses
+            .iter()
+            .map(|address| WinResult {
+                class: "foot".into(),
+                title: "".into(),
+                address: (*address).into(),
+                pid: 1,
+                workspace: "1".into(),
+                wm_type: WMEnum::Niri,
+                workspaces: vec![],
+                kind: WinKind::Window,
+                members: vec![],
+                summary: None,
+                app: None,
+            })
+            .collect()
+    }
+
+    fn addresses(windows: &[WinResult]) -> Vec<&str> {
+        windows.iter().map(|w| w.address.as_str()).collect()
+    }
+
+    #[test]
+    fn test_focus_order() {
+        let current = windows(&["a", "b", "c"]);
+        let current = focus(&current, "c").unwrap();
+        assert_eq!(addresses(&current), vec!["c", "a", "b"]);
+        assert!(focus(&current, "x").is_none());
+
+        let merged = merge(&current, windows(&["a", "b", "d", "c"]));
+        assert_eq!(addresses(&merged), vec!["d", "c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_actions() {
+        let mut window = windows(&["a"]).remove(0);
+        window.workspaces = vec!["1".into(), "2".into(), "web".into()];
+        assert_eq!(
+            window.actions(),
+            vec![
+                "Close",
+                "Move to current workspace",
+                "Pull to current output",
+                "Toggle floating",
+                "Toggle fullscreen",
+                "Move to workspace 2",
+                "Move to workspace web",
+            ]
+        );
+
+        window.wm_type = WMEnum::Toplevel;
+        assert_eq!(window.actions(), vec!["Close"]);
+    }
+
+    #[test]
+    fn test_targets() {
+        let mut windows = windows(&["a", "b", "c"]);
+        windows[2].workspace = "web".into();
+        let workspace = |id: &str, name: &str, output: &str| Workspace {
+            id: id.into(),
+            name: name.into(),
+            output: output.into(),
+        };
+        let workspaces = vec![
+            workspace("3", "1", "eDP-1"),
+            workspace("4", "web", "eDP-1"),
+            workspace("5", "mail", "DP-1"),
+        ];
+
+        let targets = targets(&windows, &workspaces, &WMEnum::Niri);
+        let summary: Vec<_> = targets
+            .iter()
+            .map(|t| (t.kind, t.address.as_str(), t.extra().unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (WinKind::Window, "a", "1"),
+                (WinKind::Window, "b", "1"),
+                (WinKind::Window, "c", "web"),
+                (WinKind::Workspace, "3", "eDP-1 · 2 windows"),
+                (WinKind::Workspace, "4", "eDP-1 · 1 window"),
+                (WinKind::Workspace, "5", "DP-1 · 0 windows"),
+                (WinKind::Output, "DP-1", "1 workspace · 0 windows"),
+                (WinKind::Output, "eDP-1", "2 workspaces · 3 windows"),
+            ]
+        );
+        // windows can be moved to empty workspaces as well
+        let names: Vec<_> = targets[0].workspaces.iter().map(|ws| ws.as_str()).collect();
+        assert_eq!(names, vec!["1", "mail", "web"]);
+        assert_eq!(targets[4].name(), "web");
+        assert_eq!(targets[4].members.len(), 1);
+        assert!(targets[4].actions().is_empty());
+    }
+}
@@ -0,0 +1,465 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use chin_tools::{aanyhow, AResult, AnyhowContext};
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use tracing::error;
+
+use super::ipc::Ipc;
+use super::{WMEnum, WinEvent, WinKind, WinResult, Workspace};
+
+lazy_static! {
+    static ref NIRI: Option<Niri> = Niri::from_env()
+        .inspect_err(|e| error!("unable to find niri: {}", e))
+        .ok();
+}
+
+pub fn client() -> AResult<&'static Niri> {
+    NIRI.as_ref().context("niri socket is not available")
+}
+
+/// Niri speaks one JSON request and one JSON reply per line on `$NIRI_SOCKET`,
+/// then closes the connection.
+pub struct Niri {
+    ipc: Ipc,
+}
+
+impl Niri {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            ipc: Ipc::new(path, false),
+        }
+    }
+
+    pub fn from_env() -> AResult<Self> {
+        Ok(Self::new(
+            std::env::var("NIRI_SOCKET")
+                .context("NIRI_SOCKET is not set")?
+                .into(),
+        ))
+    }
+
+    pub fn list_windows(&self) -> AResult<Vec<WinResult>> {
+        let workspaces = self.request(&json!("Workspaces"))?;
+        let windows = self.request(&json!("Windows"))?;
+        parse_windows(
+            &parse_workspaces(workspaces.get("Workspaces").unwrap_or(&Value::Null))?,
+            windows.get("Windows").unwrap_or(&Value::Null),
+        )
+    }
+
+    pub fn list_workspaces(&self) -> AResult<Vec<Workspace>> {
+        let workspaces = self.request(&json!("Workspaces"))?;
+        parse_workspaces(workspaces.get("Workspaces").unwrap_or(&Value::Null))
+    }
+
+    pub fn focus_workspace(&self, id: &str) -> AResult<()> {
+        self.action(json!({
+            "FocusWorkspace": { "reference": { "Id": id.parse::<u64>()? } }
+        }))
+    }
+
+    pub fn focus_output(&self, name: &str) -> AResult<()> {
+        self.action(json!({ "FocusMonitor": { "output": name } }))
+    }
+
+    pub fn focus_window(&self, id: &str) -> AResult<()> {
+        self.action(json!({ "FocusWindow": { "id": id.parse::<u64>()? } }))
+    }
+
+    pub fn close_window(&self, id: &str) -> AResult<()> {
+        self.action(json!({ "CloseWindow": { "id": id.parse::<u64>()? } }))
+    }
+
+    /// `workspace` is a name, or the index of an unnamed workspace as `parse_workspaces` labels it.
+    pub fn move_to_workspace(&self, id: &str, workspace: &str) -> AResult<()> {
+        let target = self.workspace(|ws| match ws["name"].as_str() {
+            Some(name) => name == workspace,
+            None => ws["idx"].as_u64().map(|idx| idx.to_string()).as_deref() == Some(workspace),
+        })?;
+        self.move_window(id, target, false)
+    }
+
+    pub fn move_to_current_workspace(&self, id: &str) -> AResult<()> {
+        let target = self.workspace(|ws| ws["is_focused"] == true)?;
+        self.move_window(id, target, true)
+    }
+
+    pub fn toggle_floating(&self, id: &str) -> AResult<()> {
+        self.action(json!({ "ToggleWindowFloating": { "id": id.parse::<u64>()? } }))
+    }
+
+    pub fn toggle_fullscreen(&self, id: &str) -> AResult<()> {
+        self.action(json!({ "FullscreenWindow": { "id": id.parse::<u64>()? } }))
+    }
+
+    pub fn pull_to_current_output(&self, id: &str) -> AResult<()> {
+        let output = self.request(&json!("FocusedOutput"))?;
+        let output = output["FocusedOutput"]["name"]
+            .as_str()
+            .context("niri has no focused output")?;
+        self.action(json!({
+            "MoveWindowToMonitor": { "id": id.parse::<u64>()?, "output": output }
+        }))
+    }
+
+    fn move_window(&self, id: &str, workspace: u64, focus: bool) -> AResult<()> {
+        self.action(json!({
+            "MoveWindowToWorkspace": {
+                "window_id": id.parse::<u64>()?,
+                "reference": { "Id": workspace },
+                "focus": focus,
+            }
+        }))
+    }
+
+    fn workspace(&self, matches: impl Fn(&Value) -> bool) -> AResult<u64> {
+        let workspaces = self.request(&json!("Workspaces"))?;
+        workspaces["Workspaces"]
+            .as_array()
+            .and_then(|all| all.iter().find(|ws| matches(ws)))
+            .and_then(|ws| ws["id"].as_u64())
+            .context("no such niri workspace")
+    }
+
+    fn action(&self, action: Value) -> AResult<()> {
+        self.request(&json!({ "Action": action }))?;
+        Ok(())
+    }
+
+    /// `EventStream` takes over its connection, so it gets one of its own.
+    pub fn watch(&self, on_event: &mut dyn FnMut(WinEvent)) -> AResult<()> {
+        let mut stream = BufReader::new(self.ipc.connect()?);
+        stream
+            .get_mut()
+            .write_all((json!("EventStream").to_string() + "\n").as_bytes())?;
+
+        let mut line = String::new();
+        stream.read_line(&mut line)?;
+        reply(&line, "EventStream")?;
+
+        let mut state = EventState::default();
+        loop {
+            line.clear();
+            if stream.read_line(&mut line)? == 0 {
+                return Err(aanyhow!("niri closed the event stream"));
+            }
+            for event in state.apply(&serde_json::from_str(&line)?)? {
+                on_event(event);
+            }
+        }
+    }
+
+    fn request(&self, request: &Value) -> AResult<Value> {
+        let line = request.to_string() + "\n";
+        let raw = self.ipc.request(|stream| {
+            stream.get_mut().write_all(line.as_bytes())?;
+            let mut reply = String::new();
+            if stream.read_line(&mut reply)? == 0 {
+                return Err(aanyhow!("niri closed the connection"));
+            }
+            Ok(reply)
+        })?;
+        reply(&raw, request)
+    }
+}
+
+fn reply(reply: &str, request: impl std::fmt::Display) -> AResult<Value> {
+    let mut reply: Value = serde_json::from_str(reply)?;
+    if let Some(err) = reply.get("Err") {
+        return Err(aanyhow!("niri refused {}: {}", request, err));
+    }
+    reply
+        .get_mut("Ok")
+        .map(Value::take)
+        .context("niri reply is neither Ok nor Err")
+}
+
+/// What the event stream told so far; niri starts it with the full workspace and window lists.
+#[derive(Default)]
+struct EventState {
+    workspaces: Option<Vec<Workspace>>,
+    windows: Option<Vec<Value>>,
+}
+
+impl EventState {
+    fn apply(&mut self, event: &Value) -> AResult<Vec<WinEvent>> {
+        let Some((kind, body)) = event.as_object().and_then(|e| e.iter().next()) else {
+            return Ok(vec![]);
+        };
+
+        let mut events = vec![];
+        match kind.as_str() {
+            "WorkspacesChanged" => {
+                let workspaces = parse_workspaces(&body["workspaces"])?;
+                events.push(WinEvent::Workspaces(workspaces.clone()));
+                self.workspaces = Some(workspaces);
+            }
+            "WindowsChanged" => {
+                let windows = body["windows"].as_array().cloned().unwrap_or_default();
+                if let Some(focused) = windows.iter().find(|w| w["is_focused"] == true) {
+                    events.push(WinEvent::Focused(focused["id"].to_string().into()));
+                }
+                self.windows = Some(windows);
+            }
+            "WindowOpenedOrChanged" => {
+                let window = &body["window"];
+                let windows = self.windows.get_or_insert_with(Vec::new);
+                match windows.iter_mut().find(|w| w["id"] == window["id"]) {
+                    Some(known) => *known = window.clone(),
+                    None => windows.push(window.clone()),
+                }
+                if window["is_focused"] == true {
+                    self.focus(&window["id"]);
+                    events.push(WinEvent::Focused(window["id"].to_string().into()));
+                }
+            }
+            "WindowClosed" => {
+                if let Some(windows) = self.windows.as_mut() {
+                    windows.retain(|w| w["id"] != body["id"]);
+                }
+            }
+            "WindowFocusChanged" => {
+                self.focus(&body["id"]);
+                if body["id"].is_i64() {
+                    return Ok(vec![WinEvent::Focused(body["id"].to_string().into())]);
+                }
+                return Ok(vec![]);
+            }
+            _ => return Ok(vec![]),
+        }
+
+        let (Some(workspaces), Some(windows)) = (self.workspaces.as_ref(), self.windows.as_ref())
+        else {
+            return Ok(events);
+        };
+        let windows = parse_windows(workspaces, &Value::Array(windows.clone()))?;
+        // the list goes before the focus so it lands on a known window
+        let at = events
+            .iter()
+            .position(|e| matches!(e, WinEvent::Focused(_)))
+            .unwrap_or(events.len());
+        events.insert(at, WinEvent::Windows(windows));
+        Ok(events)
+    }
+
+    /// Niri only reports the newly focused window, the others keep their stale flag.
+    fn focus(&mut self, id: &Value) {
+        for window in self.windows.iter_mut().flatten() {
+            window["is_focused"] = Value::Bool(window["id"] == *id);
+        }
+    }
+}
+
+/// The `Workspaces` reply ordered by output and index; unnamed workspaces are named after
+/// their index.
+pub fn parse_workspaces(workspaces: &Value) -> AResult<Vec<Workspace>> {
+    let mut workspaces: Vec<&Value> = workspaces
+        .as_array()
+        .context("niri workspace output is not a valid json")?
+        .iter()
+        .collect();
+    workspaces.sort_by_key(|e| (e["output"].as_str(), e["idx"].as_u64()));
+
+    Ok(workspaces
+        .into_iter()
+        .filter_map(|e| {
+            let name = if let Some(Some(name)) = e.get("name").map(|e| e.as_str()) {
+                name.to_string()
+            } else {
+                e.get("idx")?.as_i64()?.to_string()
+            };
+            Some(Workspace {
+                id: e.get("id")?.as_i64()?.to_string().into(),
+                name: name.into(),
+                output: e["output"].as_str().unwrap_or("").into(),
+            })
+        })
+        .collect())
+}
+
+/// The `Windows` reply, the most recently focused window first.
+pub fn parse_windows(workspaces: &[Workspace], windows: &Value) -> AResult<Vec<WinResult>> {
+    let ws_map: HashMap<&str, &str> = workspaces
+        .iter()
+        .map(|ws| (ws.id.as_str(), ws.name.as_str()))
+        .collect();
+
+    let mut windows: Vec<&Value> = windows
+        .as_array()
+        .context("niri windows output is not a valid json")?
+        .iter()
+        .collect();
+    // `focus_timestamp` only exists since niri 25.05
+    windows.sort_by_key(|e| {
+        let timestamp = &e["focus_timestamp"];
+        (
+            e["is_focused"] != true,
+            Reverse((timestamp["secs"].as_u64(), timestamp["nanos"].as_u64())),
+        )
+    });
+
+    let vec: Vec<WinResult> = windows
+        .into_iter()
+        .filter_map(|e| {
+            Some(WinResult {
+                class: e.get("app_id")?.as_str()?.into(),
+                title: e.get("title")?.as_str()?.into(),
+                address: e.get("id")?.as_i64()?.to_string().into(),
+                pid: e.get("pid")?.as_i64()?,
+                workspace: (*ws_map.get(e.get("workspace_id")?.as_i64()?.to_string().as_str())?)
+                    .into(),
+                wm_type: WMEnum::Niri,
+                workspaces: vec![],
+                kind: WinKind::Window,
+                members: vec![],
+                summary: None,
+                app: None,
+            })
+        })
+        .collect();
+
+    Ok(vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+
+    use super::super::ipc::fake::FakeServer;
+    use super::super::WinEvent;
+    use super::Niri;
+
+    fn respond(request: &[u8]) -> Vec<u8> {
+        let reply = match std::str::from_utf8(request).unwrap().trim_end() {
+            r#""Workspaces""# => format!(
+                r#"{{"Ok":{{"Workspaces":{}}}}}"#,
+                include_str!("fixtures/niri_workspaces.json").trim_end()
+            ),
+            r#""Windows""# => format!(
+                r#"{{"Ok":{{"Windows":{}}}}}"#,
+                include_str!("fixtures/niri_windows.json").trim_end()
+            ),
+            r#""FocusedOutput""# => r#"{"Ok":{"FocusedOutput":{"name":"eDP-1"}}}"#.to_string(),
+            action if action.starts_with(r#"{"Action""#) && !action.contains("99") => {
+                r#"{"Ok":"Handled"}"#.to_string()
+            }
+            r#""EventStream""# => format!(
+                "{}\n{}",
+                r#"{"Ok":"Handled"}"#,
+                include_str!("fixtures/niri_events.jsonl").trim_end()
+            ),
+            _ => r#"{"Err":"no such window"}"#.to_string(),
+        };
+        (reply + "\n").into_bytes()
+    }
+
+    #[test]
+    fn test_niri() {
+        let server = FakeServer::serve("niri", false, respond);
+        let niri = Niri::new(server.path.clone());
+
+        let windows = niri.list_windows().unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].class.as_str(), "org.gnome.Nautilus");
+        assert_eq!(windows[0].address.as_str(), "12");
+        assert_eq!(windows[0].workspace.as_str(), "web");
+        // unnamed workspaces fall back to their index
+        assert_eq!(windows[1].workspace.as_str(), "2");
+
+        let workspaces = niri.list_workspaces().unwrap();
+        let names: Vec<_> = workspaces.iter().map(|ws| ws.name.as_str()).collect();
+        assert_eq!(names, vec!["web", "2"]);
+        assert_eq!(workspaces[1].id.as_str(), "4");
+
+        niri.focus_window("12").unwrap();
+        assert!(niri.focus_window("99").is_err());
+        // one connection per request
+        assert_eq!(server.connections(), 5);
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let server = FakeServer::serve("niri-events", false, respond);
+        let niri = Niri::new(server.path.clone());
+
+        let mut events = vec![];
+        let stopped = niri.watch(&mut |event| {
+            events.push(match event {
+                WinEvent::Windows(windows) => windows
+                    .iter()
+                    .map(|w| format!("{}:{}", w.address, w.title))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                WinEvent::Focused(address) => format!("focus {}", address),
+                WinEvent::Workspaces(workspaces) => workspaces
+                    .iter()
+                    .map(|ws| format!("{}:{}@{}", ws.id, ws.name, ws.output))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            })
+        });
+        assert!(stopped.is_err());
+        assert_eq!(
+            events,
+            vec![
+                "3:web@eDP-1,4:2@eDP-1",
+                "12:Home,15:vim",
+                "focus 12",
+                "21:Mozilla Firefox,12:Home,15:vim",
+                "focus 21",
+                "focus 15",
+                "15:vim README.org,12:Home,21:Mozilla Firefox",
+                "focus 15",
+                "15:vim README.org,21:Mozilla Firefox",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_actions() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+        let server = FakeServer::serve("niri-actions", false, move |request| {
+            let value: Value = serde_json::from_slice(request).unwrap();
+            if value.get("Action").is_some() {
+                recorded.lock().unwrap().push(value["Action"].clone());
+            }
+            respond(request)
+        });
+        let niri = Niri::new(server.path.clone());
+
+        niri.close_window("12").unwrap();
+        niri.move_to_workspace("12", "2").unwrap();
+        niri.move_to_current_workspace("15").unwrap();
+        niri.toggle_floating("15").unwrap();
+        niri.toggle_fullscreen("15").unwrap();
+        niri.pull_to_current_output("15").unwrap();
+        niri.focus_workspace("4").unwrap();
+        niri.focus_output("DP-1").unwrap();
+        assert!(niri.move_to_workspace("12", "mail").is_err());
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                json!({ "CloseWindow": { "id": 12 } }),
+                json!({ "MoveWindowToWorkspace": {
+                    "window_id": 12, "reference": { "Id": 4 }, "focus": false
+                } }),
+                json!({ "MoveWindowToWorkspace": {
+                    "window_id": 15, "reference": { "Id": 3 }, "focus": true
+                } }),
+                json!({ "ToggleWindowFloating": { "id": 15 } }),
+                json!({ "FullscreenWindow": { "id": 15 } }),
+                json!({ "MoveWindowToMonitor": { "id": 15, "output": "eDP-1" } }),
+                json!({ "FocusWorkspace": { "reference": { "Id": 4 } } }),
+                json!({ "FocusMonitor": { "output": "DP-1" } }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::io::{Read, Write};
+
+use chin_tools::{aanyhow, AResult, AnyhowContext};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use tracing::error;
+
+use super::ipc::Ipc;
+use super::{WMEnum, WinKind, WinResult, Workspace};
+
+const MAGIC: &[u8] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const GET_TREE: u32 = 4;
+
+/// Workspace sway keeps hidden windows on.
+const SCRATCHPAD: &str = "__i3_scratch";
+
+lazy_static! {
+    static ref SWAY: Option<Ipc> = std::env::var("SWAYSOCK")
+        .inspect_err(|e| error!("unable to find sway: {}", e))
+        .ok()
+        .map(|path| Ipc::new(path.into(), true));
+}
+
+pub fn focus_window(id: &str) -> AResult<()> {
+    run_command(id, "focus")
+}
+
+/// Runs `command` on the window with the con_id `id`.
+pub fn run_command(id: &str, command: &str) -> AResult<()> {
+    let id: i64 = id.parse()?;
+    self::command(&format!("[con_id={}] {}", id, command))
+}
+
+pub fn command(command: &str) -> AResult<()> {
+    let reply = request(RUN_COMMAND, command)?;
+    let replies: Vec<Value> = serde_json::from_str(&reply)?;
+    match replies.iter().find_map(|r| r.get("error")?.as_str()) {
+        Some(err) => Err(aanyhow!("unable to {}: {}", command, err)),
+        None => Ok(()),
+    }
+}
+
+pub fn list_windows() -> AResult<Vec<WinResult>> {
+    parse_tree(&request(GET_TREE, "")?)
+}
+
+/// Sway workspaces are addressed by name.
+pub fn list_workspaces() -> AResult<Vec<Workspace>> {
+    parse_workspaces(&request(GET_WORKSPACES, "")?)
+}
+
+pub fn parse_workspaces(workspaces: &str) -> AResult<Vec<Workspace>> {
+    let workspaces: Vec<Value> = serde_json::from_str(workspaces)?;
+    Ok(workspaces
+        .iter()
+        .filter_map(|ws| {
+            let name = ws.get("name")?.as_str()?;
+            Some(Workspace {
+                id: name.into(),
+                name: name.into(),
+                output: ws.get("output")?.as_str()?.into(),
+            })
+        })
+        .collect())
+}
+
+fn request(kind: u32, payload: &str) -> AResult<String> {
+    let ipc = SWAY.as_ref().context("SWAYSOCK is not set")?;
+    let message = encode(kind, payload);
+    ipc.request(|stream| {
+        stream.get_mut().write_all(&message)?;
+
+        let mut header = [0; 14];
+        stream.read_exact(&mut header)?;
+        let (reply_kind, len) = decode_header(&header)?;
+        if reply_kind != kind {
+            return Err(aanyhow!("unexpected reply {} to {}", reply_kind, kind));
+        }
+
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body)?;
+        Ok(String::from_utf8(body)?)
+    })
+}
+
+/// `"i3-ipc" <length: u32> <type: u32> <payload>`, in native byte order.
+fn encode(kind: u32, payload: &str) -> Vec<u8> {
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    message
+}
+
+fn decode_header(header: &[u8; 14]) -> AResult<(u32, usize)> {
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(aanyhow!("not an i3-ipc reply"));
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into()?);
+    let kind = u32::from_ne_bytes(header[10..14].try_into()?);
+    Ok((kind, len as usize))
+}
+
+/// The reply of `GET_TREE`, windows are the leaves with a pid.
+pub fn parse_tree(tree: &str) -> AResult<Vec<WinResult>> {
+    let root: Value = serde_json::from_str(tree)?;
+    let mut windows = vec![];
+    collect(&root, None, &mut windows);
+    Ok(windows)
+}
+
+fn collect(node: &Value, workspace: Option<&str>, windows: &mut Vec<WinResult>) {
+    let workspace = match node.get("type").and_then(Value::as_str) {
+        Some("workspace") => match node.get("name").and_then(Value::as_str) {
+            Some(SCRATCHPAD) => return,
+            name => name,
+        },
+        _ => workspace,
+    };
+
+    if let Some(window) = to_window(node, workspace) {
+        windows.push(window);
+    }
+
+    for key in ["nodes", "floating_nodes"] {
+        if let Some(children) = node.get(key).and_then(Value::as_array) {
+            for child in children {
+                collect(child, workspace, windows);
+            }
+        }
+    }
+}
+
+fn to_window(node: &Value, workspace: Option<&str>) -> Option<WinResult> {
+    let pid = node.get("pid")?.as_i64()?;
+    // xwayland windows have no app_id but an X11 class
+    let class = node
+        .get("app_id")
+        .and_then(Value::as_str)
+        .or_else(|| node.get("window_properties")?.get("class")?.as_str())?;
+
+    Some(WinResult {
+        class: class.into(),
+        title: node
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .into(),
+        address: node.get("id")?.as_i64()?.to_string().into(),
+        pid,
+        workspace: workspace.unwrap_or("").into(),
+        wm_type: WMEnum::Sway,
+        workspaces: vec![],
+        kind: WinKind::Window,
+        members: vec![],
+        summary: None,
+        app: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_header, encode, parse_tree, parse_workspaces, GET_TREE};
+
+    #[test]
+    fn test_parse_tree() {
+        let windows = parse_tree(include_str!("fixtures/sway_tree.json")).unwrap();
+        let summary: Vec<_> = windows
+            .iter()
+            .map(|w| (w.address.as_str(), w.class.as_str(), w.workspace.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("4", "foot", "1"),
+                ("5", "firefox", "2:web"),
+                ("7", "steam", "2:web"),
+            ]
+        );
+        assert_eq!(windows[1].title.as_str(), "Mozilla Firefox");
+        assert_eq!(windows[1].pid, 1844);
+    }
+
+    #[test]
+    fn test_parse_workspaces() {
+        let workspaces = parse_workspaces(include_str!("fixtures/sway_workspaces.json")).unwrap();
+        let summary: Vec<_> = workspaces
+            .iter()
+            .map(|ws| (ws.id.as_str(), ws.output.as_str()))
+            .collect();
+        assert_eq!(summary, vec![("1", "eDP-1"), ("2:web", "eDP-1")]);
+    }
+
+    #[test]
+    fn test_message() {
+        let message = encode(GET_TREE, "");
+        assert_eq!(&message[..6], b"i3-ipc");
+        assert_eq!(message.len(), 14);
+        assert_eq!(
+            decode_header(message[..14].try_into().unwrap()).unwrap(),
+            (GET_TREE, 0)
+        );
+    }
+}
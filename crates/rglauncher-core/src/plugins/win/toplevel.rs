@@ -0,0 +1,331 @@
+//! Any compositor speaking `zwlr_foreign_toplevel_manager_v1` (wlroots based ones),
+//! falling back to the list-only `ext_foreign_toplevel_list_v1`.
+
+use chin_tools::{aanyhow, AResult};
+use serde::Deserialize;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat::WlSeat};
+use wayland_client::{event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+    ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+    ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use super::{WMEnum, WinKind, WinResult};
+
+/// What the compositor told about one toplevel.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ToplevelInfo {
+    pub app_id: String,
+    pub title: String,
+    /// Only sent by `ext_foreign_toplevel_list_v1`.
+    pub identifier: Option<String>,
+    #[serde(default)]
+    pub activated: bool,
+}
+
+enum Handle {
+    Wlr(ZwlrForeignToplevelHandleV1),
+    Ext(ExtForeignToplevelHandleV1),
+}
+
+struct Toplevel {
+    handle: Handle,
+    info: ToplevelInfo,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct State {
+    toplevels: Vec<Toplevel>,
+}
+
+impl State {
+    fn find(&mut self, id: wayland_client::backend::ObjectId) -> Option<&mut Toplevel> {
+        self.toplevels.iter_mut().find(|t| match &t.handle {
+            Handle::Wlr(h) => h.id() == id,
+            Handle::Ext(h) => h.id() == id,
+        })
+    }
+}
+
+struct Session {
+    queue: EventQueue<State>,
+    state: State,
+    seat: Option<WlSeat>,
+    can_activate: bool,
+}
+
+fn connect() -> AResult<Session> {
+    let conn = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+    let qh = queue.handle();
+
+    let can_activate = match globals.bind::<ZwlrForeignToplevelManagerV1, _, _>(&qh, 1..=3, ()) {
+        Ok(_) => true,
+        Err(_) => {
+            globals
+                .bind::<ExtForeignToplevelListV1, _, _>(&qh, 1..=1, ())
+                .map_err(|_| aanyhow!("the compositor exposes no foreign toplevel protocol"))?;
+            false
+        }
+    };
+    let seat = globals.bind::<WlSeat, _, _>(&qh, 1..=1, ()).ok();
+
+    let mut state = State::default();
+    // the first roundtrip announces the toplevels, the second delivers their properties
+    queue.roundtrip(&mut state)?;
+    queue.roundtrip(&mut state)?;
+
+    Ok(Session {
+        queue,
+        state,
+        seat,
+        can_activate,
+    })
+}
+
+pub fn list_windows() -> AResult<Vec<WinResult>> {
+    let session = connect()?;
+    let infos: Vec<ToplevelInfo> = session
+        .state
+        .toplevels
+        .into_iter()
+        .filter(|t| !t.closed)
+        .map(|t| t.info)
+        .collect();
+    Ok(to_results(&infos))
+}
+
+pub fn focus_window(address: &str) -> AResult<()> {
+    act(address, |handle, seat| handle.activate(seat))
+}
+
+pub fn close_window(address: &str) -> AResult<()> {
+    act(address, |handle, _| handle.close())
+}
+
+/// The protocol has no stable ids, so the address is the position in the announced list
+/// plus the app id, checked again before acting on it.
+fn act(address: &str, action: impl FnOnce(&ZwlrForeignToplevelHandleV1, &WlSeat)) -> AResult<()> {
+    let (index, app_id) = address
+        .split_once(':')
+        .and_then(|(index, app_id)| Some((index.parse::<usize>().ok()?, app_id)))
+        .ok_or_else(|| aanyhow!("invalid toplevel address: {}", address))?;
+
+    let mut session = connect()?;
+    if !session.can_activate {
+        return Err(aanyhow!(
+            "the compositor doesn't allow controlling toplevels"
+        ));
+    }
+    let seat = session.seat.as_ref().ok_or_else(|| aanyhow!("no seat"))?;
+
+    let open: Vec<&Toplevel> = session
+        .state
+        .toplevels
+        .iter()
+        .filter(|t| !t.closed)
+        .collect();
+    let target = open
+        .get(index)
+        .filter(|t| t.info.app_id == app_id)
+        .or_else(|| open.iter().find(|t| t.info.app_id == app_id))
+        .ok_or_else(|| aanyhow!("toplevel {} is gone", address))?;
+
+    if let Handle::Wlr(handle) = &target.handle {
+        action(handle, seat);
+    }
+    session.queue.roundtrip(&mut session.state)?;
+    Ok(())
+}
+
+pub fn to_results(infos: &[ToplevelInfo]) -> Vec<WinResult> {
+    infos
+        .iter()
+        .enumerate()
+        .filter(|(_, info)| !info.app_id.is_empty() || !info.title.is_empty())
+        .map(|(index, info)| WinResult {
+            class: info.app_id.as_str().into(),
+            title: info.title.as_str().into(),
+            address: match &info.identifier {
+                Some(identifier) => identifier.as_str().into(),
+                None => format!("{}:{}", index, info.app_id).into(),
+            },
+            pid: 0,
+            workspace: "".into(),
+            wm_type: WMEnum::Toplevel,
+            workspaces: vec![],
+            kind: WinKind::Window,
+            members: vec![],
+            summary: None,
+            app: None,
+        })
+        .collect()
+}
+
+/// `zwlr_foreign_toplevel_handle_v1.state` is an array of native endian u32.
+fn is_activated(state: &[u8]) -> bool {
+    state
+        .chunks_exact(4)
+        .filter_map(|c| c.try_into().ok().map(u32::from_ne_bytes))
+        .any(|s| s == zwlr_foreign_toplevel_handle_v1::State::Activated as u32)
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlSeat,
+        _: <WlSeat as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            state.toplevels.push(Toplevel {
+                handle: Handle::Wlr(toplevel),
+                info: ToplevelInfo::default(),
+                closed: false,
+            });
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(toplevel) = state.find(handle.id()) else {
+            return;
+        };
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.info.title = title,
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                toplevel.info.app_id = app_id
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                toplevel.info.activated = is_activated(&state)
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => toplevel.closed = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } = event {
+            state.toplevels.push(Toplevel {
+                handle: Handle::Ext(toplevel),
+                info: ToplevelInfo::default(),
+                closed: false,
+            });
+        }
+    }
+
+    event_created_child!(State, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(toplevel) = state.find(handle.id()) else {
+            return;
+        };
+        match event {
+            ext_foreign_toplevel_handle_v1::Event::Title { title } => toplevel.info.title = title,
+            ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                toplevel.info.app_id = app_id
+            }
+            ext_foreign_toplevel_handle_v1::Event::Identifier { identifier } => {
+                toplevel.info.identifier = Some(identifier)
+            }
+            ext_foreign_toplevel_handle_v1::Event::Closed => toplevel.closed = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_activated, to_results, ToplevelInfo};
+
+    #[test]
+    fn test_to_results() {
+        let infos: Vec<ToplevelInfo> =
+            serde_json::from_str(include_str!("fixtures/toplevels.json")).unwrap();
+        let windows = to_results(&infos);
+        let summary: Vec<_> = windows
+            .iter()
+            .map(|w| (w.address.as_str(), w.class.as_str(), w.title.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("0:foot", "foot", "~/crate"),
+                ("1:firefox", "firefox", "Mozilla Firefox"),
+                ("a8f3c2d1", "org.gnome.Nautilus", "Home"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_state() {
+        let state: Vec<u8> = [0u32, 2].iter().flat_map(|s| s.to_ne_bytes()).collect();
+        assert!(is_activated(&state));
+        assert!(!is_activated(&1u32.to_ne_bytes()));
+        assert!(!is_activated(&[]));
+    }
+}
@@ -1,6 +1,17 @@
 use std::fs::DirEntry;
+use std::path::PathBuf;
 
-use chin_tools::AResult;
+use chin_tools::{aanyhow, AResult};
+
+/// `$XDG_CACHE_HOME/rglauncher`.
+pub fn cache_home() -> AResult<PathBuf> {
+    let cache = match std::env::var("XDG_CACHE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var("HOME").map_err(|_| aanyhow!("HOME is not set"))?)
+            .join(".cache"),
+    };
+    Ok(cache.join("rglauncher"))
+}
 
 pub fn walk_dir<F>(dirpath: &str, filter: Option<F>) -> AResult<Vec<DirEntry>>
 where
@@ -0,0 +1,96 @@
+//! Icons rendered at the sizes they are shown at, kept as PNGs between runs.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use chin_tools::AResult;
+
+use crate::util::fs_utils;
+
+/// Names the icon theme the cached icons were looked up in.
+const THEME_STAMP: &str = "theme";
+
+/// `$XDG_CACHE_HOME/rglauncher/icons`.
+pub fn cache_dir() -> AResult<PathBuf> {
+    Ok(fs_utils::cache_home()?.join("icons"))
+}
+
+#[derive(Debug)]
+pub struct IconDiskCache {
+    dir: PathBuf,
+}
+
+impl IconDiskCache {
+    /// Everything cached for another theme is removed, the same names resolve to other
+    /// files now.
+    pub fn open(dir: PathBuf, theme: &str) -> AResult<Self> {
+        let stamp = dir.join(THEME_STAMP);
+        if std::fs::read_to_string(&stamp).ok().as_deref() != Some(theme) {
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir)?;
+            }
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(&stamp, theme)?;
+        }
+        Ok(Self { dir })
+    }
+
+    /// Where `source` rendered at `size` pixels is kept; changing the file changes it.
+    pub fn path_for(&self, source: &Path, size: u32) -> AResult<PathBuf> {
+        let meta = std::fs::metadata(source)?;
+        let mtime = meta.modified()?.duration_since(UNIX_EPOCH)?.as_nanos();
+        let key = format!("{}|{}|{}|{}", source.display(), mtime, meta.len(), size);
+        Ok(self.dir.join(format!("{:x}.png", md5::compute(key))))
+    }
+
+    pub fn get(&self, source: &Path, size: u32) -> Option<PathBuf> {
+        self.path_for(source, size)
+            .ok()
+            .filter(|path| path.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IconDiskCache;
+
+    #[test]
+    fn test_icon_cache() {
+        let root = std::env::temp_dir().join(format!("rglauncher-icons-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let source = root.join("foot.svg");
+        std::fs::write(&source, "<svg/>").unwrap();
+        let dir = root.join("cache");
+
+        let cache = IconDiskCache::open(dir.clone(), "Adwaita").unwrap();
+        assert!(cache.get(&source, 28).is_none());
+        let rendered = cache.path_for(&source, 28).unwrap();
+        std::fs::write(&rendered, "png").unwrap();
+        assert_eq!(cache.get(&source, 28), Some(rendered.clone()));
+        assert!(cache.get(&source, 256).is_none());
+
+        // a changed source is rendered again
+        std::fs::write(&source, "<svg></svg>").unwrap();
+        assert!(cache.get(&source, 28).is_none());
+        // so is one only touched, the same content can be another icon after an update
+        std::fs::write(&source, "<svg/>").unwrap();
+        let rendered = cache.path_for(&source, 28).unwrap();
+        std::fs::write(&rendered, "png").unwrap();
+        let touched = std::fs::metadata(&source).unwrap().modified().unwrap()
+            + std::time::Duration::from_secs(60);
+        let file = std::fs::File::options().write(true).open(&source).unwrap();
+        file.set_modified(touched).unwrap();
+        assert!(cache.get(&source, 28).is_none());
+        std::fs::write(&source, "<svg/>").unwrap();
+        let rendered = cache.path_for(&source, 28).unwrap();
+        std::fs::write(&rendered, "png").unwrap();
+
+        let cache = IconDiskCache::open(dir.clone(), "Adwaita").unwrap();
+        assert!(cache.get(&source, 28).is_some());
+        let cache = IconDiskCache::open(dir, "Papirus").unwrap();
+        assert!(cache.get(&source, 28).is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,330 @@
+//! Icon lookup following the freedesktop Icon Theme Specification.
+//!
+//! <https://specifications.freedesktop.org/icon-theme-spec/latest/>
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+const FALLBACK_THEME: &str = "hicolor";
+
+#[derive(Debug, Clone, PartialEq)]
+enum DirType {
+    Fixed,
+    Scalable { min: u32, max: u32 },
+    Threshold(u32),
+}
+
+#[derive(Debug, Clone)]
+struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    kind: DirType,
+}
+
+impl ThemeDir {
+    fn matches(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirType::Fixed => self.size == size,
+            DirType::Scalable { min, max } => min <= size && size <= max,
+            DirType::Threshold(t) => self.size.saturating_sub(t) <= size && size <= self.size + t,
+        }
+    }
+
+    fn distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (min, max) = match self.kind {
+            DirType::Fixed => (self.size, self.size),
+            DirType::Scalable { min, max } => (min, max),
+            DirType::Threshold(t) => (self.size.saturating_sub(t), self.size + t),
+        };
+        // at most one of them is not zero
+        (min * self.scale).saturating_sub(wanted) + wanted.saturating_sub(max * self.scale)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Theme {
+    name: String,
+    /// Every base directory containing the theme; an icon may be in any of them.
+    roots: Vec<PathBuf>,
+    dirs: Vec<ThemeDir>,
+    inherits: Vec<String>,
+}
+
+impl Theme {
+    fn load(base_dirs: &[PathBuf], name: &str) -> Option<Self> {
+        let roots: Vec<PathBuf> = base_dirs
+            .iter()
+            .map(|base| base.join(name))
+            .filter(|root| root.is_dir())
+            .collect();
+        let index = roots
+            .iter()
+            .find_map(|root| std::fs::read_to_string(root.join("index.theme")).ok())?;
+        let sections = parse_index(&index);
+        let theme = sections.get("Icon Theme")?;
+
+        let list = |key: &str| -> Vec<String> {
+            theme
+                .get(key)
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let dirs = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| {
+                let section = sections.get(path.as_str())?;
+                let number = |key: &str| section.get(key).and_then(|v| v.parse::<u32>().ok());
+                let size = number("Size")?;
+                let kind = match section.get("Type").map(String::as_str) {
+                    Some("Fixed") => DirType::Fixed,
+                    Some("Scalable") => DirType::Scalable {
+                        min: number("MinSize").unwrap_or(size),
+                        max: number("MaxSize").unwrap_or(size),
+                    },
+                    _ => DirType::Threshold(number("Threshold").unwrap_or(2)),
+                };
+                Some(ThemeDir {
+                    path,
+                    size,
+                    scale: number("Scale").unwrap_or(1),
+                    kind,
+                })
+            })
+            .collect();
+
+        Some(Theme {
+            name: name.to_string(),
+            roots,
+            dirs,
+            inherits: list("Inherits"),
+        })
+    }
+
+    fn lookup(&self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let candidates = || {
+            self.dirs.iter().flat_map(move |dir| {
+                self.roots.iter().flat_map(move |root| {
+                    EXTENSIONS.iter().map(move |ext| {
+                        (dir, root.join(&dir.path).join(format!("{}.{}", name, ext)))
+                    })
+                })
+            })
+        };
+
+        if let Some((_, path)) =
+            candidates().find(|(dir, path)| dir.matches(size, scale) && path.is_file())
+        {
+            return Some(path);
+        }
+        candidates()
+            .filter(|(_, path)| path.is_file())
+            .min_by_key(|(dir, _)| dir.distance(size, scale))
+            .map(|(_, path)| path)
+    }
+}
+
+/// `[Section]` and `Key=Value` lines of an `index.theme`, localized keys are kept as is.
+fn parse_index(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = None;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(section.to_string());
+            sections.entry(section.to_string()).or_default();
+        } else if let (Some(section), Some((key, value))) = (current.as_ref(), line.split_once('='))
+        {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+/// `$HOME/.icons`, `$XDG_DATA_HOME/icons`, `$XDG_DATA_DIRS/icons` and `/usr/share/pixmaps`.
+pub fn base_dirs() -> Vec<PathBuf> {
+    let home = std::env::var("HOME").map(PathBuf::from).ok();
+    let mut dirs = vec![];
+    if let Some(home) = home.as_ref() {
+        dirs.push(home.join(".icons"));
+    }
+    match std::env::var("XDG_DATA_HOME") {
+        Ok(data) if !data.is_empty() => dirs.push(PathBuf::from(data).join("icons")),
+        _ => dirs.extend(home.map(|h| h.join(".local/share/icons"))),
+    }
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(
+        data_dirs
+            .split(':')
+            .filter(|d| !d.is_empty())
+            .map(|d| PathBuf::from(d).join("icons")),
+    );
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+    dirs
+}
+
+/// A theme with everything it inherits from, ending with hicolor.
+#[derive(Debug, Clone)]
+pub struct IconTheme {
+    name: String,
+    base_dirs: Vec<PathBuf>,
+    themes: Vec<Theme>,
+}
+
+impl IconTheme {
+    pub fn new(base_dirs: Vec<PathBuf>, name: &str) -> Self {
+        let mut themes: Vec<Theme> = vec![];
+        let mut pending = vec![name.to_string()];
+        // depth first, parents in the order they are listed
+        while let Some(next) = pending.pop() {
+            if next == FALLBACK_THEME || themes.iter().any(|t| t.name == next) {
+                continue;
+            }
+            if let Some(theme) = Theme::load(&base_dirs, &next) {
+                pending.extend(theme.inherits.iter().rev().cloned());
+                themes.push(theme);
+            }
+        }
+        themes.extend(Theme::load(&base_dirs, FALLBACK_THEME));
+
+        Self {
+            name: name.to_string(),
+            base_dirs,
+            themes,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `icon` is what an `Icon=` key holds: a name, a name with an extension or a path.
+    pub fn lookup(&self, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        if icon.is_empty() {
+            return None;
+        }
+        if Path::new(icon).is_absolute() {
+            return Path::new(icon).is_file().then(|| PathBuf::from(icon));
+        }
+
+        let name = match icon.rsplit_once('.') {
+            Some((stem, ext)) if EXTENSIONS.contains(&ext) => stem,
+            _ => icon,
+        };
+        self.themes
+            .iter()
+            .find_map(|theme| theme.lookup(name, size, scale))
+            .or_else(|| lookup_in(&self.base_dirs, name))
+    }
+}
+
+/// Unthemed icons lying directly in one of `dirs`.
+pub fn lookup_in(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    dirs.iter().find_map(|dir| {
+        EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name, ext)))
+            .find(|path| path.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::IconTheme;
+
+    fn theme() -> (PathBuf, IconTheme) {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/util/fixtures");
+        let theme = IconTheme::new(vec![root.join("icons"), root.join("pixmaps")], "Test");
+        (root, theme)
+    }
+
+    fn found(theme: &IconTheme, icon: &str, size: u32) -> Option<String> {
+        let path = theme.lookup(icon, size, 1)?;
+        Some(path.to_str()?.split("fixtures/").nth(1)?.to_string())
+    }
+
+    #[test]
+    fn test_lookup() {
+        let (root, theme) = theme();
+        let found = |icon, size| found(&theme, icon, size);
+
+        assert_eq!(
+            found("foot", 16).as_deref(),
+            Some("icons/Test/16x16/apps/foot.png")
+        );
+        assert_eq!(
+            found("foot", 48).as_deref(),
+            Some("icons/Test/48x48/apps/foot.png")
+        );
+        // the closest size when none matches
+        assert_eq!(
+            found("foot", 28).as_deref(),
+            Some("icons/Test/16x16/apps/foot.png")
+        );
+        assert_eq!(
+            found("firefox", 28).as_deref(),
+            Some("icons/Test/scalable/apps/firefox.svg")
+        );
+        assert_eq!(
+            found("firefox.svg", 256).as_deref(),
+            Some("icons/Test/scalable/apps/firefox.svg")
+        );
+        // inherited, then hicolor
+        assert_eq!(
+            found("org.gnome.Nautilus", 28).as_deref(),
+            Some("icons/Base/32x32/apps/org.gnome.Nautilus.png")
+        );
+        assert_eq!(
+            found("steam", 28).as_deref(),
+            Some("icons/hicolor/48x48/apps/steam.png")
+        );
+        assert_eq!(
+            found("steam", 256).as_deref(),
+            Some("icons/hicolor/256x256/apps/steam.png")
+        );
+        // unthemed
+        assert_eq!(found("legacy", 28).as_deref(), Some("pixmaps/legacy.xpm"));
+        assert_eq!(found("missing", 28), None);
+
+        let absolute = root.join("pixmaps/legacy.xpm");
+        assert_eq!(
+            theme.lookup(absolute.to_str().unwrap(), 28, 1),
+            Some(absolute.clone())
+        );
+        assert_eq!(theme.lookup("/nonexistent/icon.png", 28, 1), None);
+    }
+
+    #[test]
+    fn test_inherits() {
+        let (root, theme) = theme();
+        let names: Vec<&str> = theme.themes.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["Test", "Base", "hicolor"]);
+
+        let unknown = IconTheme::new(vec![root.join("icons")], "Unknown");
+        assert_eq!(unknown.themes.len(), 1);
+        assert!(unknown.lookup("steam", 48, 1).is_some());
+    }
+}
@@ -1,3 +1,5 @@
 pub mod fs_utils;
+pub mod icon_cache;
+pub mod icon_theme;
 pub mod score_utils;
 pub mod sqlite3_utils;
@@ -33,3 +33,46 @@ pub fn middle(origin: i64) -> i32 {
 pub fn low(origin: i64) -> i32 {
     limit_to(LOW, 1000, origin)
 }
+
+/// Levenshtein distance between `a` and `b` by chars, `None` once it exceeds `max`.
+pub fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        // every later row is at least the smallest value of this one
+        if current.iter().min().is_some_and(|m| *m > max) {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b.len()]).filter(|d| *d <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::edit_distance;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("apple", "apple", 2), Some(0));
+        assert_eq!(edit_distance("aple", "apple", 2), Some(1));
+        assert_eq!(edit_distance("recieve", "receive", 2), Some(2));
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("a", "abcd", 2), None);
+        assert_eq!(edit_distance("café", "cafe", 1), Some(1));
+        assert_eq!(edit_distance("", "ab", 2), Some(2));
+    }
+}
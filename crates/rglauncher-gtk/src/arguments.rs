@@ -1,9 +1,17 @@
 use crate::constants;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Default, Debug, Clone)]
 #[command(author = constants::PROJECT_AUTHOR, version = constants::PROJECT_VERSION, about = constants::PROJECT_DESCRIPTION)]
 pub struct Arguments {
     #[clap(long, help = "The file path of config file.")]
     pub config_file: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    #[command(about = "Load every dictionary and tell why those which fail cannot be loaded.")]
+    CheckDicts,
 }
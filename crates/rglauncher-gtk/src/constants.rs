@@ -5,3 +5,6 @@ pub const PROJECT_NAME: &str = "RGLauncher";
 pub const TMP_DIR: &str = "/tmp/rglauncher/";
 pub const UNIX_SOCKET_PATH: &str = "/tmp/rglauncher/.socket";
 pub const APP_ID: &str = "org.codeberg.wangzh.rglauncher";
+/// Pixel sizes of the icons in the sidebar rows and in the previews.
+pub const ROW_ICON_SIZE: i32 = 28;
+pub const PREVIEW_ICON_SIZE: i32 = 256;
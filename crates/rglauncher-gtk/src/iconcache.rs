@@ -1,44 +1,123 @@
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use chin_tools::{AResult, EResult};
-use fragile::Fragile;
+use gtk::gdk::Texture;
 use gtk::gio;
 use gtk::gio::MemoryInputStream;
 
 use gtk::gdk_pixbuf::Pixbuf;
-use gtk::glib::Bytes;
+use gtk::glib::{self, Bytes};
+use gtk::prelude::{ObjectExt, ObjectType, WidgetExt};
 use lazy_static::lazy_static;
 use rglcore::config::ParsedConfig;
+use rglcore::util::icon_cache::{self, IconDiskCache};
+use rglcore::util::icon_theme::{self, IconTheme};
 use smol_str::SmolStr;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
-use std::fs::read_to_string;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
+/// Loaded icons kept around, for every size they are shown at.
+const ICON_MAP_CAPACITY: usize = 512;
+
 lazy_static! {
     static ref ICON_PATHS: ArcSwap<Vec<PathBuf>> = ArcSwap::new(Arc::new(Vec::new()));
-    static ref ICON_MAP: ArcSwap<HashMap<smol_str::SmolStr, Option<Arc<Fragile<Pixbuf>>>>> =
-        ArcSwap::new(Arc::new(HashMap::new()));
     static ref ALIAS_MAP: ArcSwap<HashMap<smol_str::SmolStr, smol_str::SmolStr>> =
         ArcSwap::new(Arc::new(HashMap::new()));
-    static ref LOGO: Arc<Fragile<Pixbuf>> = Arc::new(Fragile::new(
-        load_from_svg(include_str!("../../../data/logo.svg")).unwrap()
-    ));
+    static ref ICON_THEME: ArcSwapOption<IconTheme> = ArcSwapOption::empty();
+    static ref DISK_CACHE: ArcSwapOption<IconDiskCache> = ArcSwapOption::empty();
+}
+
+thread_local! {
+    /// Only touched on the GTK thread, `None` for icons that don't exist.
+    static ICON_MAP: RefCell<Lru<Option<Texture>>> = RefCell::new(Lru::new(ICON_MAP_CAPACITY));
+    /// Images waiting for an icon being loaded.
+    static LOADING: RefCell<HashMap<SmolStr, Vec<glib::WeakRef<gtk::Image>>>> =
+        RefCell::new(HashMap::new());
+    /// The icon each image was asked to show last, rows are recycled while icons load.
+    static WANTED: RefCell<HashMap<usize, Wanted>> = RefCell::new(HashMap::new());
+    /// Images whose scale factor is followed.
+    static WATCHED: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+    /// By pixel size.
+    static LOGO: RefCell<HashMap<i32, Texture>> = RefCell::new(HashMap::new());
 }
 
-fn load_from_svg(svg_data: &str) -> AResult<Pixbuf> {
+struct Wanted {
+    name: SmolStr,
+    size: i32,
+    key: SmolStr,
+}
+
+/// Least recently used entries go first once `capacity` is reached.
+struct Lru<V> {
+    entries: HashMap<SmolStr, (u64, V)>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            tick: 0,
+            capacity,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<V> {
+        self.tick += 1;
+        let (used, value) = self.entries.get_mut(key)?;
+        *used = self.tick;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: SmolStr, value: V) {
+        self.tick += 1;
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (self.tick, value));
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn load_from_svg(svg_data: &str, pixels: i32) -> AResult<Pixbuf> {
     let image_stream = MemoryInputStream::from_bytes(&Bytes::from(svg_data.as_bytes()));
 
     Ok(Pixbuf::from_stream_at_scale(
         &image_stream,
-        256,
-        256,
+        pixels,
+        pixels,
         true,
         None::<&gio::Cancellable>,
     )?)
 }
 
+fn logo(pixels: i32) -> Texture {
+    LOGO.with_borrow_mut(|logos| {
+        logos
+            .entry(pixels)
+            .or_insert_with(|| {
+                Texture::for_pixbuf(
+                    &load_from_svg(include_str!("../../../data/logo.svg"), pixels).unwrap(),
+                )
+            })
+            .clone()
+    })
+}
+
 pub fn set_config(config: &ParsedConfig) -> EResult {
     tracing::info!("set icon config: {:?}", config.icon);
     if let Some(icon_config) = &config.icon {
@@ -63,45 +142,172 @@ pub fn set_config(config: &ParsedConfig) -> EResult {
     Ok(())
 }
 
-pub fn get_pixbuf(name: &str) -> Pixbuf {
-    if let Some(icon) = get_pixbuf_inner(name) {
-        icon.get().clone()
-    } else {
-        LOGO.get().clone()
+/// Shows the icon `name` at `size` logical pixels in `image`, rendered for the scale
+/// factor of its surface. Icons not loaded yet are loaded on the gio thread pool while
+/// the logo stands in for them.
+pub fn set_icon(image: &gtk::Image, name: &str, size: i32) {
+    watch_scale(image);
+    let theme = current_theme();
+    let scale = image.scale_factor().max(1);
+    let pixels = size * scale;
+    let wanted = Wanted {
+        name: name.into(),
+        size,
+        key: format!("{}@{}x{}", name.to_lowercase(), size, scale).into(),
+    };
+    let key = wanted.key.clone();
+    WANTED.with_borrow_mut(|all| all.insert(image.as_ptr() as usize, wanted));
+
+    let name = match ALIAS_MAP.load().get(name.to_lowercase().as_str()) {
+        Some(mapped) => mapped.clone(),
+        None => SmolStr::from(name),
+    };
+
+    if let Some(icon) = ICON_MAP.with_borrow_mut(|icons| icons.get(&key)) {
+        show(image, icon.as_ref(), pixels);
+        return;
+    }
+    show(image, None, pixels);
+
+    let first = LOADING.with_borrow_mut(|loading| {
+        let waiting = loading.entry(key.clone()).or_default();
+        waiting.push(image.downgrade());
+        waiting.len() == 1
+    });
+    if !first {
+        return;
+    }
+
+    glib::MainContext::default().spawn_local(async move {
+        let icon = gio::spawn_blocking(move || load(&theme, &name, size, scale))
+            .await
+            .ok()
+            .flatten();
+        ICON_MAP.with_borrow_mut(|icons| icons.insert(key.clone(), icon.clone()));
+
+        let waiting = LOADING.with_borrow_mut(|loading| loading.remove(&key));
+        for image in waiting.into_iter().flatten().filter_map(|i| i.upgrade()) {
+            let wanted = WANTED.with_borrow(|all| {
+                all.get(&(image.as_ptr() as usize))
+                    .is_some_and(|wanted| wanted.key == key)
+            });
+            if wanted {
+                show(&image, icon.as_ref(), pixels);
+            }
+        }
+    });
+}
+
+/// The image is unbound, icons still loading must not land in it.
+pub fn forget(image: &gtk::Image) {
+    WANTED.with_borrow_mut(|wanted| wanted.remove(&(image.as_ptr() as usize)));
+    image.clear();
+}
+
+fn show(image: &gtk::Image, icon: Option<&Texture>, pixels: i32) {
+    match icon {
+        Some(icon) => image.set_paintable(Some(icon)),
+        None => image.set_paintable(Some(&logo(pixels))),
     }
 }
 
-fn get_pixbuf_inner(name: &str) -> Option<Arc<Fragile<Pixbuf>>> {
-    let name = name.to_lowercase();
-    if let Some(icon) = ICON_MAP.load().get(name.as_str()) {
-        icon.clone()
-    } else if let Some(mapped) = ALIAS_MAP.load().get(name.as_str()) {
-        return get_pixbuf_inner(&mapped);
-    } else {
-        let icon = ICON_PATHS
-            .load()
-            .iter()
-            .filter_map(|e| {
-                let svg_path = e.join(format!("{}.svg", name));
-                if svg_path.exists() {
-                    read_to_string(svg_path)
-                        .ok()
-                        .and_then(|e| load_from_svg(&e).ok())
-                } else {
-                    None
-                }
-            })
-            .nth(0);
+/// Moving the window to a monitor with another scale renders the icon again.
+fn watch_scale(image: &gtk::Image) {
+    let id = image.as_ptr() as usize;
+    if !WATCHED.with_borrow_mut(|watched| watched.insert(id)) {
+        return;
+    }
+    image.connect_scale_factor_notify(|image| {
+        let wanted = WANTED.with_borrow(|all| {
+            all.get(&(image.as_ptr() as usize))
+                .map(|wanted| (wanted.name.clone(), wanted.size))
+        });
+        if let Some((name, size)) = wanted {
+            set_icon(image, &name, size);
+        }
+    });
+    image.connect_destroy(move |_| {
+        WATCHED.with_borrow_mut(|watched| watched.remove(&id));
+        WANTED.with_borrow_mut(|all| all.remove(&id));
+    });
+}
 
-        let icon = icon.clone().map(|e| Arc::new(Fragile::from(e)));
-        let mut icon_map: HashMap<SmolStr, Option<Arc<Fragile<Pixbuf>>>> = ICON_MAP
-            .load()
-            .iter()
-            .map(|(key, icon)| (key.clone(), icon.clone()))
-            .collect();
-        icon_map.insert(name.to_lowercase().into(), icon.clone());
-        ICON_MAP.store(Arc::new(icon_map));
+/// The theme GTK uses, loaded again when the user switches it.
+fn current_theme() -> Arc<IconTheme> {
+    let name = gtk::Settings::default()
+        .and_then(|settings| settings.gtk_icon_theme_name())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| "hicolor".to_string());
+
+    match ICON_THEME.load_full() {
+        Some(theme) if theme.name() == name => theme,
+        loaded => {
+            if loaded.is_some() {
+                ICON_MAP.with_borrow_mut(|icons| icons.clear());
+            }
+            tracing::info!("loading icon theme {}", name);
+            let theme = Arc::new(IconTheme::new(icon_theme::base_dirs(), &name));
+            ICON_THEME.store(Some(theme.clone()));
+            let disk_cache = icon_cache::cache_dir()
+                .and_then(|dir| IconDiskCache::open(dir, &name))
+                .inspect_err(|e| tracing::warn!("icons are not cached on disk: {}", e))
+                .ok();
+            DISK_CACHE.store(disk_cache.map(Arc::new));
+            theme
+        }
+    }
+}
 
-        return icon;
+/// Runs on the gio thread pool. Rendered icons are saved to the disk cache, so the next
+/// start only decodes a small PNG.
+fn load(theme: &IconTheme, name: &str, size: i32, scale: i32) -> Option<Texture> {
+    let path = find_icon(theme, name, size, scale)?;
+    let pixels = size * scale;
+    let disk_cache = DISK_CACHE.load_full();
+    if let Some(cached) = disk_cache
+        .as_ref()
+        .and_then(|c| c.get(&path, pixels as u32))
+    {
+        match Texture::from_file(&gio::File::for_path(&cached)) {
+            Ok(texture) => return Some(texture),
+            Err(e) => tracing::warn!("unable to load cached icon {:?}: {}", cached, e),
+        }
     }
+
+    let pixbuf = Pixbuf::from_file_at_scale(&path, pixels, pixels, true)
+        .inspect_err(|e| tracing::warn!("unable to load icon {:?}: {}", path, e))
+        .ok()?;
+    if let Some(disk_cache) = disk_cache.as_ref() {
+        let saved = disk_cache
+            .path_for(&path, pixels as u32)
+            .and_then(|cached| save_png(&pixbuf, &cached));
+        if let Err(e) = saved {
+            tracing::warn!("unable to cache icon {:?}: {}", path, e);
+        }
+    }
+    Some(Texture::for_pixbuf(&pixbuf))
+}
+
+/// Renamed into place, an interrupted save leaves no broken icon behind.
+fn save_png(pixbuf: &Pixbuf, path: &Path) -> EResult {
+    let partial = path.with_extension("part");
+    pixbuf.savev(&partial, "png", &[])?;
+    Ok(std::fs::rename(partial, path)?)
+}
+
+/// Configured directories win over the theme; window classes are often capitalized
+/// differently than their icons, so the lowercase name is tried as well.
+fn find_icon(theme: &IconTheme, name: &str, size: i32, scale: i32) -> Option<PathBuf> {
+    let lower = name.to_lowercase();
+    let names = match lower == name {
+        true => vec![name],
+        false => vec![name, lower.as_str()],
+    };
+    names.into_iter().find_map(|name| {
+        if Path::new(name).is_absolute() {
+            return theme.lookup(name, size as u32, scale as u32);
+        }
+        icon_theme::lookup_in(&ICON_PATHS.load(), name)
+            .or_else(|| theme.lookup(name, size as u32, scale as u32))
+    })
 }
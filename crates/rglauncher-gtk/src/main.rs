@@ -10,7 +10,7 @@ mod sidebar;
 mod sidebarrow;
 mod window;
 
-use chin_tools::{AResult, EResult};
+use chin_tools::{aanyhow, AResult, EResult};
 use clap::Parser;
 use rglcore::config::Config;
 use std::io::{Read, Write};
@@ -25,7 +25,7 @@ use crate::launcher::LauncherMsg;
 use flume::Sender;
 use std::os::unix::net::{UnixListener, UnixStream};
 
-pub fn daemon() -> EResult {
+pub fn daemon(arguments: arguments::Arguments) -> EResult {
     tracing_subscriber::fmt()
         .with_max_level(Level::INFO)
         .with_thread_ids(true)
@@ -41,7 +41,6 @@ pub fn daemon() -> EResult {
 
     let mut app = RGLApplication::new();
 
-    let arguments = arguments::Arguments::parse();
     let config = Arc::new(Config::read_from_toml_file(arguments.config_file.as_ref())?);
     iconcache::set_config(&config)?;
 
@@ -85,13 +84,52 @@ fn build_uds(app_msg_tx: &Sender<LauncherMsg>) -> AResult<()> {
     }
 }
 
+/// Prints each dictionary and whether it loads, fails when any does not.
+#[cfg(feature = "mdict-text")]
+fn check_dicts(arguments: &arguments::Arguments) -> EResult {
+    let config = Config::read_from_toml_file(arguments.config_file.as_ref())?;
+    let dict = config
+        .dict
+        .as_ref()
+        .ok_or_else(|| aanyhow!("no dictionaries are configured"))?;
+    let checked = rglcore::plugins::mdict::check(dict)?;
+    let mut failed = 0;
+    for (path, name) in checked.iter() {
+        match name {
+            Ok(name) => println!("ok      {} ({})", path.display(), name),
+            Err(e) => {
+                failed += 1;
+                println!("failed  {}: {}", path.display(), e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(aanyhow!(
+            "{} of {} dictionaries cannot be loaded",
+            failed,
+            checked.len()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "mdict-text"))]
+fn check_dicts(_arguments: &arguments::Arguments) -> EResult {
+    Err(aanyhow!("built without dictionary support"))
+}
+
 fn main() -> EResult {
+    let arguments = arguments::Arguments::parse();
+    if let Some(arguments::Command::CheckDicts) = arguments.command {
+        return check_dicts(&arguments);
+    }
+
     match UnixStream::connect(constants::UNIX_SOCKET_PATH) {
         Ok(mut stream) => {
             stream.write_all("new_window".as_bytes())?;
         }
         Err(_) => {
-            daemon()?;
+            daemon(arguments)?;
         }
     }
 
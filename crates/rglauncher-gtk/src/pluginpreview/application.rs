@@ -1,3 +1,4 @@
+use crate::constants::PREVIEW_ICON_SIZE;
 use crate::iconcache;
 use crate::pluginpreview::PluginPreview;
 use gtk::glib::object::Cast;
@@ -60,8 +61,11 @@ impl PluginPreview for AppPreview {
     }
 
     fn set_preview(&self, plugin_result: &Self::PluginResult) {
-        self.icon
-            .set_from_pixbuf(Some(&iconcache::get_pixbuf(plugin_result.icon_name.as_str())));
+        iconcache::set_icon(
+            &self.icon,
+            plugin_result.icon_name.as_str(),
+            PREVIEW_ICON_SIZE,
+        );
 
         self.name.set_label(plugin_result.app_name.as_str());
         self.exec.set_label(plugin_result.desktop_path.as_str());
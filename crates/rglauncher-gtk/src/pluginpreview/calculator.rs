@@ -8,6 +8,8 @@ pub struct CalcPreview {
     root: gtk::Grid,
     formula_buffer: gtk::TextBuffer,
     result_buffer: gtk::TextBuffer,
+    details: gtk::Grid,
+    variables: gtk::Grid,
 }
 
 impl PluginPreview for CalcPreview {
@@ -46,10 +48,28 @@ impl PluginPreview for CalcPreview {
             .build();
         preview.attach(&result_area, 0, 2, 1, 1);
 
+        let details = gtk::Grid::builder()
+            .hexpand(true)
+            .vexpand(false)
+            .valign(gtk::Align::End)
+            .css_classes(["prev-btm-box"])
+            .build();
+        preview.attach(&details, 0, 3, 1, 1);
+
+        let variables = gtk::Grid::builder()
+            .hexpand(true)
+            .vexpand(false)
+            .valign(gtk::Align::End)
+            .css_classes(["prev-btm-box"])
+            .build();
+        preview.attach(&variables, 0, 4, 1, 1);
+
         CalcPreview {
             root: preview,
             formula_buffer,
             result_buffer,
+            details,
+            variables,
         }
     }
 
@@ -60,6 +80,9 @@ impl PluginPreview for CalcPreview {
     fn set_preview(&self, plugin_result: &Self::PluginResult) {
         self.formula_buffer.set_text(plugin_result.formula.as_str());
         self.result_buffer.set_text(plugin_result.result.as_str());
+
+        super::fill_pairs(&self.details, &plugin_result.details);
+        super::fill_pairs(&self.variables, &plugin_result.variables);
     }
 
     fn get_id(&self) -> &str {
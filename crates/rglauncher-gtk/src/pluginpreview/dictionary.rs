@@ -1,63 +1,200 @@
-use super::PluginPreview;
-use rglcore::config::DictConfig;
-use rglcore::plugins::mdict::DictResult;
+use super::{build_history_bar, PluginPreview};
+use rglcore::plugins::mdict::{self, DictResult, History};
 
 use glib::object::Cast;
-use gtk::prelude::WidgetExt;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::{BoxExt, ButtonExt, WidgetExt};
 use gtk::Widget;
-use webkit6::prelude::WebViewExt;
+use std::cell::RefCell;
+use std::rc::Rc;
+use webkit6::prelude::{PolicyDecisionExt, WebViewExt};
 use webkit6::UserContentInjectedFrames::AllFrames;
 use webkit6::UserStyleLevel::User;
-use webkit6::{UserStyleSheet, WebView};
+use webkit6::{
+    NavigationPolicyDecision, PolicyDecisionType, URISchemeRequest, UserStyleSheet, WebContext,
+    WebView,
+};
+
+/// Titles of the dictionary sections, the configured stylesheets are inlined in theirs.
+const SECTION_CSS: &str = "
+details.dictionary > summary { font-weight: bold; cursor: pointer; margin: 0.4em 0; }
+details.dictionary .languages { font-weight: normal; opacity: 0.6; }
+";
 
 pub struct DictPreview {
+    root: gtk::Box,
     pub webview: WebView,
+    nav: Navigator,
 }
 
-impl DictPreview {
-    pub fn add_csses(&self, config: Option<&DictConfig>) {
-        if let Some(dirpath) = config.as_ref().map(|c| c.dir_path.as_str()) {
-            if let Some(ucm) = self.webview.user_content_manager() {
-                ucm.remove_all_style_sheets();
-                let paths = rglcore::util::fs_utils::walk_dir(
-                    dirpath,
-                    Some(|p: &str| p.to_lowercase().ends_with(".css")),
-                );
-
-                if let Ok(des) = paths {
-                    for de in des {
-                        if let Ok(css) = std::fs::read_to_string(de.path()) {
-                            let ss = UserStyleSheet::new(css.as_str(), AllFrames, User, &[], &[]);
-                            ucm.add_style_sheet(&ss);
-                        }
-                    }
-                }
+/// Shows the entries of the history, `sound://` links resolve against the shown one.
+#[derive(Clone)]
+struct Navigator {
+    webview: WebView,
+    history: Rc<RefCell<History>>,
+    back: gtk::Button,
+    forward: gtk::Button,
+}
+
+impl Navigator {
+    fn show(&self) {
+        let history = self.history.borrow();
+        if let Some(result) = history.current() {
+            let html_content = result.html.replace("\0", " ");
+            self.webview
+                .load_html(html_content.as_str(), Some(&result.base_uri()));
+        }
+        self.back.set_sensitive(history.can_go_back());
+        self.forward.set_sensitive(history.can_go_forward());
+    }
+
+    /// Looks the word of an `entry://` or `bword://` link up in place.
+    fn follow(&self, word: &str) {
+        match mdict::lookup(word) {
+            Some(result) => {
+                self.history.borrow_mut().push(result);
+                self.show();
             }
+            None => tracing::debug!("{} is in no dictionary", word),
+        }
+    }
 
-            self.webview.set_css_classes(&["webview"]);
+    fn go_back(&self) {
+        if self.history.borrow_mut().back().is_some() {
+            self.show();
+        }
+    }
+
+    fn go_forward(&self) {
+        if self.history.borrow_mut().forward().is_some() {
+            self.show();
+        }
+    }
+}
+
+impl DictPreview {
+    pub fn add_csses(&self) {
+        if let Some(ucm) = self.webview.user_content_manager() {
+            ucm.remove_all_style_sheets();
+            ucm.add_style_sheet(&UserStyleSheet::new(SECTION_CSS, AllFrames, User, &[], &[]));
         }
+
+        self.webview.set_css_classes(&["webview"]);
     }
 }
 
+/// Images, stylesheets and sounds of the entries, read from the `.mdd` files off the
+/// GTK thread.
+fn serve_resource(request: &URISchemeRequest) {
+    let request = request.clone();
+    let uri = request.uri().map(|uri| uri.to_string()).unwrap_or_default();
+    glib::MainContext::default().spawn_local(async move {
+        let resource = gio::spawn_blocking(move || mdict::lookup_resource(&uri)).await;
+        match resource {
+            Ok(Ok((data, mime))) => {
+                let length = data.len() as i64;
+                let stream = gio::MemoryInputStream::from_bytes(&glib::Bytes::from_owned(data));
+                request.finish(&stream, length, Some(mime));
+            }
+            Ok(Err(e)) => {
+                tracing::debug!("unable to serve {:?}: {}", request.uri(), e);
+                request.finish_error(&mut glib::Error::new(
+                    gio::IOErrorEnum::NotFound,
+                    &e.to_string(),
+                ));
+            }
+            Err(_) => request.finish_error(&mut glib::Error::new(
+                gio::IOErrorEnum::Failed,
+                "resource lookup panicked",
+            )),
+        }
+    });
+}
+
+fn play(webview: &WebView, uri: &str) {
+    let script = format!(
+        "new Audio('{}').play();",
+        uri.replace('\\', "\\\\").replace('\'', "\\'")
+    );
+    webview.evaluate_javascript(&script, None, None, None::<&gio::Cancellable>, |result| {
+        if let Err(e) = result {
+            tracing::warn!("unable to play sound: {}", e);
+        }
+    });
+}
+
 impl PluginPreview for DictPreview {
     type PluginResult = DictResult;
 
     fn new() -> Self {
+        if let Some(context) = WebContext::default() {
+            context.register_uri_scheme(mdict::RESOURCE_SCHEME, serve_resource);
+        }
+
+        let root = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .vexpand(true)
+            .hexpand(true)
+            .build();
+        let (back, forward) = build_history_bar(&root);
+
         let webview = WebView::new();
         webview.set_vexpand(true);
         webview.set_hexpand(true);
         webview.set_focusable(false);
+        root.append(&webview);
+
+        let nav = Navigator {
+            webview: webview.clone(),
+            history: Default::default(),
+            back: back.clone(),
+            forward: forward.clone(),
+        };
+        let n = nav.clone();
+        back.connect_clicked(move |_| n.go_back());
+        let n = nav.clone();
+        forward.connect_clicked(move |_| n.go_forward());
+
+        let n = nav.clone();
+        webview.connect_decide_policy(move |webview, decision, kind| {
+            if kind != PolicyDecisionType::NavigationAction {
+                return false;
+            }
+            let Some(uri) = decision
+                .downcast_ref::<NavigationPolicyDecision>()
+                .and_then(|d| d.navigation_action())
+                .and_then(|mut action| action.request())
+                .and_then(|request| request.uri())
+            else {
+                return false;
+            };
+            if let Some(word) = mdict::entry_word(&uri) {
+                decision.ignore();
+                n.follow(&word);
+                return true;
+            }
+            let sound = n.history.borrow().current().and_then(|r| r.sound_uri(&uri));
+            match sound {
+                Some(sound) => {
+                    decision.ignore();
+                    play(webview, &sound);
+                    true
+                }
+                None => false,
+            }
+        });
 
-        DictPreview { webview }
+        DictPreview { root, webview, nav }
     }
 
     fn get_preview(&self) -> Widget {
-        self.webview.clone().upcast()
+        self.root.clone().upcast()
     }
 
     fn set_preview(&self, plugin_result: &Self::PluginResult) {
-        let html_content = plugin_result.html.replace("\0", " ");
-        self.webview.load_html(html_content.as_str(), None);
+        self.nav.history.borrow_mut().reset(plugin_result.clone());
+        self.nav.show();
         self.webview
             .set_background_color(&gtk::gdk::RGBA::new(0., 0., 0., 0.));
     }
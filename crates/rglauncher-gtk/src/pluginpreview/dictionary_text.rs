@@ -0,0 +1,155 @@
+use super::{build_history_bar, PluginPreview};
+use rglcore::plugins::mdict::{self, markup, DictResult, History};
+
+use glib::object::Cast;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::{AdjustmentExt, BoxExt, ButtonExt, MediaStreamExt, WidgetExt};
+use gtk::Widget;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Entries as Pango markup in a label, for builds without WebKit.
+pub struct DictPreview {
+    root: gtk::Box,
+    nav: Navigator,
+}
+
+/// Shows the entries of the history, `sound://` links resolve against the shown one.
+#[derive(Clone)]
+struct Navigator {
+    scrolled: gtk::ScrolledWindow,
+    label: gtk::Label,
+    history: Rc<RefCell<History>>,
+    back: gtk::Button,
+    forward: gtk::Button,
+}
+
+impl Navigator {
+    fn show(&self) {
+        let history = self.history.borrow();
+        if let Some(result) = history.current() {
+            self.label
+                .set_markup(&markup::to_pango(&result.html.replace('\0', " ")));
+            self.scrolled.vadjustment().set_value(0.);
+        }
+        self.back.set_sensitive(history.can_go_back());
+        self.forward.set_sensitive(history.can_go_forward());
+    }
+
+    /// Looks the word of an `entry://` or `bword://` link up in place.
+    fn follow(&self, word: &str) {
+        match mdict::lookup(word) {
+            Some(result) => {
+                self.history.borrow_mut().push(result);
+                self.show();
+            }
+            None => tracing::debug!("{} is in no dictionary", word),
+        }
+    }
+
+    fn go_back(&self) {
+        if self.history.borrow_mut().back().is_some() {
+            self.show();
+        }
+    }
+
+    fn go_forward(&self) {
+        if self.history.borrow_mut().forward().is_some() {
+            self.show();
+        }
+    }
+}
+
+/// Sounds are read off the GTK thread and kept in `playing` until the next one.
+fn play(uri: String, playing: Rc<RefCell<Option<gtk::MediaFile>>>) {
+    glib::MainContext::default().spawn_local(async move {
+        match gio::spawn_blocking(move || mdict::lookup_resource(&uri)).await {
+            Ok(Ok((data, _))) => {
+                let stream = gio::MemoryInputStream::from_bytes(&glib::Bytes::from_owned(data));
+                let media = gtk::MediaFile::for_input_stream(&stream);
+                media.play();
+                playing.replace(Some(media));
+            }
+            Ok(Err(e)) => tracing::warn!("unable to play sound: {}", e),
+            Err(_) => tracing::warn!("sound lookup panicked"),
+        }
+    });
+}
+
+impl PluginPreview for DictPreview {
+    type PluginResult = DictResult;
+
+    fn new() -> Self {
+        let label = gtk::Label::builder()
+            .use_markup(true)
+            .wrap(true)
+            .wrap_mode(gtk::pango::WrapMode::WordChar)
+            .selectable(true)
+            .xalign(0.)
+            .valign(gtk::Align::Start)
+            .margin_start(15)
+            .margin_end(15)
+            .margin_top(15)
+            .margin_bottom(15)
+            .build();
+        let scrolled = gtk::ScrolledWindow::builder()
+            .vexpand(true)
+            .hexpand(true)
+            .focusable(false)
+            .child(&label)
+            .build();
+        let root = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .vexpand(true)
+            .hexpand(true)
+            .build();
+        let (back, forward) = build_history_bar(&root);
+        root.append(&scrolled);
+
+        let nav = Navigator {
+            scrolled,
+            label: label.clone(),
+            history: Default::default(),
+            back: back.clone(),
+            forward: forward.clone(),
+        };
+        let n = nav.clone();
+        back.connect_clicked(move |_| n.go_back());
+        let n = nav.clone();
+        forward.connect_clicked(move |_| n.go_forward());
+
+        let playing: Rc<RefCell<Option<gtk::MediaFile>>> = Rc::new(RefCell::new(None));
+        let n = nav.clone();
+        label.connect_activate_link(move |_, uri| {
+            if let Some(word) = mdict::entry_word(uri) {
+                n.follow(&word);
+                return glib::Propagation::Stop;
+            }
+            let sound = n.history.borrow().current().and_then(|r| r.sound_uri(uri));
+            match sound {
+                Some(sound) => {
+                    play(sound, playing.clone());
+                    glib::Propagation::Stop
+                }
+                // web links open in the browser
+                None => glib::Propagation::Proceed,
+            }
+        });
+
+        DictPreview { root, nav }
+    }
+
+    fn get_preview(&self) -> Widget {
+        self.root.clone().upcast()
+    }
+
+    fn set_preview(&self, plugin_result: &Self::PluginResult) {
+        self.nav.history.borrow_mut().reset(plugin_result.clone());
+        self.nav.show();
+    }
+
+    fn get_id(&self) -> &str {
+        rglcore::plugins::mdict::TYPE_ID
+    }
+}
@@ -6,6 +6,8 @@ use crate::pluginpreview::calculator::CalcPreview;
 use crate::pluginpreview::clipboard::ClipPreview;
 #[cfg(feature = "mdict")]
 use crate::pluginpreview::dictionary::DictPreview;
+#[cfg(all(feature = "mdict-text", not(feature = "mdict")))]
+use crate::pluginpreview::dictionary_text::DictPreview;
 #[cfg(feature = "wmwin")]
 use crate::pluginpreview::windows::WMWindowPreview;
 use flume::Receiver;
@@ -14,6 +16,7 @@ use gtk::pango::WrapMode::WordChar;
 use gtk::prelude::{GridExt, WidgetExt};
 use gtk::Align::Center;
 use gtk::BinLayout;
+use chin_tools::SharedStr;
 use rglcore::config::{Config, ParsedConfig};
 use rglcore::plugins::{PRWrapper, PluginResult, PluginResultEnum};
 use std::cell::RefCell;
@@ -27,6 +30,8 @@ mod calculator;
 mod clipboard;
 #[cfg(feature = "mdict")]
 mod dictionary;
+#[cfg(all(feature = "mdict-text", not(feature = "mdict")))]
+mod dictionary_text;
 #[cfg(feature = "wmwin")]
 mod windows;
 
@@ -54,18 +59,18 @@ pub struct PluginPreviewBuilder {
     calc_preview: CalcPreview,
     #[cfg(feature = "clip")]
     clip_preview: ClipPreview,
-    #[cfg(feature = "mdict")]
+    #[cfg(feature = "mdict-text")]
     dict_preview: DictPreview,
     #[cfg(feature = "wmwin")]
     wind_preview: WMWindowPreview,
 }
 
 impl PluginPreviewBuilder {
-    pub fn new(stack: &gtk::Stack, config: &Config) -> Self {
+    pub fn new(stack: &gtk::Stack, _config: &Config) -> Self {
         let app_preview = AppPreview::new();
-        #[cfg(feature = "mdict")]
+        #[cfg(feature = "mdict-text")]
         let dict_preview = DictPreview::new();
-        #[cfg(feature = "mdict")]
+        #[cfg(feature = "mdict-text")]
         stack.add_named(&dict_preview.get_preview(), Some(dict_preview.get_id()));
 
         #[cfg(feature = "calc")]
@@ -97,7 +102,7 @@ impl PluginPreviewBuilder {
         stack.set_visible_child(&default);
 
         #[cfg(feature = "mdict")]
-        dict_preview.add_csses(config.dict.as_ref());
+        dict_preview.add_csses();
 
         PluginPreviewBuilder {
             stack: stack.clone(),
@@ -106,7 +111,7 @@ impl PluginPreviewBuilder {
             calc_preview,
             #[cfg(feature = "clip")]
             clip_preview,
-            #[cfg(feature = "mdict")]
+            #[cfg(feature = "mdict-text")]
             dict_preview,
             #[cfg(feature = "wmwin")]
             wind_preview,
@@ -116,7 +121,7 @@ impl PluginPreviewBuilder {
     pub fn set_preview(&self, pr: Option<&PluginResultEnum>) -> Option<()> {
         if let Some(plugin_result) = pr {
             match plugin_result {
-                #[cfg(feature = "mdict")]
+                #[cfg(feature = "mdict-text")]
                 PluginResultEnum::MDict(r) => self.dict_preview.set_preview(r),
                 PluginResultEnum::Calc(r) => self.calc_preview.set_preview(r),
                 PluginResultEnum::App(r) => self.app_preview.set_preview(r),
@@ -156,6 +161,55 @@ fn build_pair_line(grid: &gtk::Grid, row: i32, title: &str) -> gtk::Label {
     right
 }
 
+fn fill_pairs(grid: &gtk::Grid, pairs: &[(SharedStr, SharedStr)]) {
+    while let Some(child) = grid.first_child() {
+        grid.remove(&child);
+    }
+    for (row, (label, value)) in pairs.iter().enumerate() {
+        build_pair_line(grid, row as i32, &format!("{}: ", label)).set_label(value.as_str());
+    }
+    grid.set_visible(!pairs.is_empty());
+}
+
+/// Back and forward buttons of the dictionary previews, mouse buttons 8 and 9 on `root`
+/// do the same.
+#[cfg(feature = "mdict-text")]
+fn build_history_bar(root: &gtk::Box) -> (gtk::Button, gtk::Button) {
+    use gtk::prelude::{BoxExt, ButtonExt, GestureSingleExt};
+
+    let back = gtk::Button::builder()
+        .icon_name("go-previous-symbolic")
+        .tooltip_text("Back")
+        .focusable(false)
+        .sensitive(false)
+        .build();
+    let forward = gtk::Button::builder()
+        .icon_name("go-next-symbolic")
+        .tooltip_text("Forward")
+        .focusable(false)
+        .sensitive(false)
+        .build();
+    let bar = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(4)
+        .css_classes(["prev-btm-box"])
+        .build();
+    bar.append(&back);
+    bar.append(&forward);
+    root.append(&bar);
+
+    let click = gtk::GestureClick::builder().button(0).build();
+    let (b, f) = (back.clone(), forward.clone());
+    click.connect_pressed(move |gesture, _, _, _| match gesture.current_button() {
+        8 => b.emit_clicked(),
+        9 => f.emit_clicked(),
+        _ => {}
+    });
+    root.add_controller(click);
+
+    (back, forward)
+}
+
 fn get_seprator() -> gtk::Separator {
     gtk::Separator::builder()
         .hexpand(true)
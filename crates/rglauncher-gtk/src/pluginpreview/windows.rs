@@ -1,6 +1,8 @@
+use crate::constants::PREVIEW_ICON_SIZE;
 use crate::iconcache;
 use crate::pluginpreview::PluginPreview;
-use rglcore::plugins::win::WinResult;
+use chin_tools::SharedStr;
+use rglcore::plugins::win::{WinKind, WinResult};
 use rglcore::plugins::PluginResult;
 
 use gtk::glib::object::Cast;
@@ -13,7 +15,9 @@ pub struct WMWindowPreview {
     preview: gtk::Widget,
     big_pic: gtk::Image,
     title: gtk::Label,
-    workspace: gtk::Label,
+    details: gtk::Grid,
+    members: gtk::Grid,
+    actions: gtk::Grid,
 }
 
 impl PluginPreview for WMWindowPreview {
@@ -47,15 +51,28 @@ impl PluginPreview for WMWindowPreview {
         // preview.attach(&title, 0, 2, 1, 1);
         r#box.append(&title);
 
+        // the windows on a workspace or output
+        let members = gtk::Grid::builder()
+            .hexpand(true)
+            .vexpand(false)
+            .css_classes(["prev-btm-box"])
+            .build();
+        r#box.append(&members);
+
         let sep = super::get_seprator();
-        let extra = gtk::Grid::builder()
+        let details = gtk::Grid::builder()
             .hexpand(true)
             .vexpand(false)
             .valign(End)
             .css_classes(["prev-btm-box"])
             .build(); // preview.attach(&extra, 0, 3, 1, 1);
 
-        let workspace = super::build_pair_line(&extra, 1, "Workspace: ");
+        let actions = gtk::Grid::builder()
+            .hexpand(true)
+            .vexpand(false)
+            .valign(End)
+            .css_classes(["prev-btm-box"])
+            .build();
 
         let sw = gtk::ScrolledWindow::builder()
             .vexpand(true)
@@ -71,13 +88,16 @@ impl PluginPreview for WMWindowPreview {
 
         tb.append(&sw);
         tb.append(&sep);
-        tb.append(&extra);
+        tb.append(&details);
+        tb.append(&actions);
 
         WMWindowPreview {
             preview: tb.upcast(),
             big_pic,
             title,
-            workspace,
+            details,
+            members,
+            actions,
         }
     }
 
@@ -88,9 +108,31 @@ impl PluginPreview for WMWindowPreview {
     fn set_preview(&self, plugin_result: &Self::PluginResult) {
         self.title.set_text(plugin_result.title.as_str());
 
-        self.workspace.set_label(&plugin_result.workspace);
-        self.big_pic
-            .set_from_pixbuf(Some(&iconcache::get_pixbuf(plugin_result.icon_name())));
+        let windows: SharedStr = plugin_result.members.len().to_string().into();
+        let details: Vec<(SharedStr, SharedStr)> = match plugin_result.kind {
+            WinKind::Window => plugin_result
+                .app
+                .iter()
+                .map(|app| ("Application".into(), app.app_name.clone()))
+                .chain([("Workspace".into(), plugin_result.workspace.clone())])
+                .collect(),
+            WinKind::Workspace => vec![
+                ("Output".into(), plugin_result.workspace.clone()),
+                ("Windows".into(), windows),
+            ],
+            WinKind::Output => vec![("Windows".into(), windows)],
+        };
+        super::fill_pairs(&self.details, &details);
+        super::fill_pairs(&self.members, &plugin_result.members);
+        iconcache::set_icon(&self.big_pic, plugin_result.icon_name(), PREVIEW_ICON_SIZE);
+
+        let actions: Vec<(SharedStr, SharedStr)> = plugin_result
+            .actions()
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| (format!("Alt+{}", i + 1).into(), label.into()))
+            .collect();
+        super::fill_pairs(&self.actions, &actions);
     }
 
     fn get_id(&self) -> &str {
@@ -68,6 +68,21 @@ impl ResultHolder {
             .expect("unable to send result to sidebar");
     }
 
+    fn select(&self, run: impl Fn(&PRWrapper)) {
+        let Some(id) = self.current_index else {
+            return;
+        };
+        if let Some(Some(pr)) = self.signal_and_results.as_ref().map(|(_, r)| r.get(id as usize)) {
+            run(pr);
+            self.launcher_tx
+                .send(LauncherMsg::SelectSomething)
+                .expect("unable to send select");
+            self.dispatch_tx
+                .send(DispatchMsg::SetHistory(pr.clone()))
+                .expect("unable to set history");
+        }
+    }
+
     fn accept_messages(&mut self) {
         let interval = Duration::from_millis(30);
         let mut received_something = false;
@@ -117,22 +132,9 @@ impl ResultHolder {
                             _ => {}
                         }
                     }
-                    ResultMsg::SelectSomething => match self.current_index.clone() {
-                        None => {}
-                        Some(id) => {
-                            if let Some(Some(pr)) =
-                                self.signal_and_results.as_ref().map(|(_, r)| r.get(id as usize))
-                            {
-                                pr.on_enter();
-                                self.launcher_tx
-                                    .send(LauncherMsg::SelectSomething)
-                                    .expect("unable to send select");
-                                self.dispatch_tx
-                                    .send(DispatchMsg::SetHistory(pr.clone()))
-                                    .expect("unable to set history");
-                            }
-                        }
-                    },
+                    ResultMsg::SelectSomething => self.select(|pr| pr.on_enter()),
+                    ResultMsg::SelectAlternative => self.select(|pr| pr.on_alt_enter()),
+                    ResultMsg::SelectAction(index) => self.select(|pr| pr.on_action(index)),
                 },
                 Err(_ex) => {
                     // error!("unable to receive message: {:?}", ex);
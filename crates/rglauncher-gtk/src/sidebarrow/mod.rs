@@ -1,5 +1,6 @@
 mod imp;
 
+use crate::constants::ROW_ICON_SIZE;
 use crate::iconcache;
 use chin_tools::utils::string_util;
 use gtk::glib;
@@ -27,8 +28,7 @@ impl SidebarRow {
     pub fn arrange_sidebar(&self, plugin_result: &PluginResultEnum) {
         let imp = self.imp();
 
-        imp.image
-            .set_from_pixbuf(Some(&iconcache::get_pixbuf(plugin_result.icon_name())));
+        iconcache::set_icon(&imp.image, plugin_result.icon_name(), ROW_ICON_SIZE);
 
         let name = plugin_result.name();
 
@@ -56,6 +56,6 @@ impl SidebarRow {
 
     pub fn unbind_all(&self) {
         let imp = self.imp();
-        imp.image.clear();
+        iconcache::forget(&imp.image);
     }
 }
@@ -131,7 +131,24 @@ impl RGWindow {
         controller.connect_key_pressed(clone!(
             #[strong]
             entry,
-            move |_, key, _keycode, _| {
+            move |_, key, _keycode, state| {
+                // Alt+1..9 runs the selected result's actions
+                let action = key
+                    .to_unicode()
+                    .and_then(|c| c.to_digit(10))
+                    .filter(|d| *d > 0 && state.contains(gdk::ModifierType::ALT_MASK));
+                if let Some(digit) = action {
+                    result_tx
+                        .send(ResultMsg::SelectAction(digit as usize - 1))
+                        .expect("select action");
+                    inputbar_tx
+                        .send(InputMessage::Clear)
+                        .expect("unable to clear");
+                    window_tx
+                        .send(WindowMsg::Close)
+                        .expect("unable to close window");
+                    return glib::Propagation::Stop;
+                }
                 match key {
                     gdk::Key::Up => {
                         sidebar_tx.send(SidebarMsg::PreviousItem).unwrap();
@@ -148,9 +165,12 @@ impl RGWindow {
                         glib::Propagation::Stop
                     }
                     gdk::Key::Return => {
-                        result_tx
-                            .send(ResultMsg::SelectSomething)
-                            .expect("select something");
+                        let msg = if state.contains(gdk::ModifierType::SHIFT_MASK) {
+                            ResultMsg::SelectAlternative
+                        } else {
+                            ResultMsg::SelectSomething
+                        };
+                        result_tx.send(msg).expect("select something");
                         inputbar_tx
                             .send(InputMessage::Clear)
                             .expect("unable to clear");